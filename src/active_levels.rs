@@ -0,0 +1,163 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{level::LevelName, level_spawning::SpawnQueue};
+
+/// Where a level is in its lifecycle.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LevelState {
+    /// The level's entities are still being spawned, a few each frame.
+    Loading,
+    Active,
+    /// The player has left the level, and it will despawn unless they come back in time.
+    Resetting {
+        time_left: f32,
+    },
+    Despawned,
+}
+
+impl LevelState {
+    /// Whether the level's entities exist in the world.
+    pub fn is_spawned(&self) -> bool {
+        !matches!(self, Self::Despawned)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ActiveLevel {
+    pub state: LevelState,
+    /// Where the level's origin is in the world.
+    pub shift: Vec3,
+}
+
+/// The state of every level that has been spawned, and which one the player is in.
+#[derive(Resource, Default)]
+pub struct ActiveLevels {
+    levels: HashMap<LevelName, ActiveLevel>,
+    current: Option<LevelName>,
+    transitions: Vec<LevelStateChanged>,
+}
+
+impl ActiveLevels {
+    pub fn get(&self, level: &LevelName) -> Option<&ActiveLevel> {
+        self.levels.get(level)
+    }
+
+    /// Levels that have never been spawned are [`LevelState::Despawned`].
+    pub fn state(&self, level: &LevelName) -> LevelState {
+        self.levels
+            .get(level)
+            .map_or(LevelState::Despawned, |level| level.state)
+    }
+
+    /// The world position of the level's origin, if it is spawned.
+    pub fn shift(&self, level: &LevelName) -> Option<Vec3> {
+        self.levels
+            .get(level)
+            .filter(|level| level.state.is_spawned())
+            .map(|level| level.shift)
+    }
+
+    /// Whether the level is loading or active, and not about to despawn.
+    pub fn is_active(&self, level: &LevelName) -> bool {
+        matches!(self.state(level), LevelState::Loading | LevelState::Active)
+    }
+
+    /// The level the player is currently in.
+    pub fn current(&self) -> Option<&LevelName> {
+        self.current.as_ref()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&LevelName, &ActiveLevel)> {
+        self.levels.iter()
+    }
+
+    pub(crate) fn set_current(&mut self, level: LevelName) {
+        self.current = Some(level);
+    }
+
+    pub(crate) fn set_state(&mut self, level: &LevelName, state: LevelState, shift: Vec3) {
+        let from = self.state(level);
+        self.levels
+            .insert(level.clone(), ActiveLevel { state, shift });
+        if std::mem::discriminant(&from) != std::mem::discriminant(&state) {
+            self.transitions.push(LevelStateChanged {
+                level: level.clone(),
+                from,
+                to: state,
+            });
+        }
+    }
+
+    /// Counts down the resetting levels, returning the ones whose time has run out.
+    pub(crate) fn tick_resetting(&mut self, delta: f32) -> Vec<LevelName> {
+        let mut expired = Vec::new();
+        for (level, active) in self.levels.iter_mut() {
+            if let LevelState::Resetting { time_left } = &mut active.state {
+                if *time_left < 0.0 {
+                    expired.push(level.clone());
+                }
+                *time_left -= delta;
+            }
+        }
+        expired
+    }
+}
+
+/// Sent whenever a level moves from one [`LevelState`] to another.
+#[derive(Event, Clone, Debug)]
+pub struct LevelStateChanged {
+    pub level: LevelName,
+    pub from: LevelState,
+    pub to: LevelState,
+}
+
+/// Sent once all of a level's entities have been spawned, and it is ready to be entered.
+#[derive(Event, Clone, Debug)]
+pub struct LevelLoaded {
+    pub level: LevelName,
+    pub shift: Vec3,
+}
+
+/// Sent once a level's entities have been despawned.
+#[derive(Event, Clone, Debug)]
+pub struct LevelUnloaded {
+    pub level: LevelName,
+}
+
+/// Finishes loading levels whose entities have spawned, and sends the events for every transition.
+pub(crate) fn update_level_states_system(
+    mut active_levels: ResMut<ActiveLevels>,
+    spawn_queue: Res<SpawnQueue>,
+    mut state_changed: EventWriter<LevelStateChanged>,
+    mut loaded: EventWriter<LevelLoaded>,
+    mut unloaded: EventWriter<LevelUnloaded>,
+) {
+    let loading: Vec<(LevelName, Vec3)> = active_levels
+        .iter()
+        .filter(|(level, active)| {
+            active.state == LevelState::Loading && !spawn_queue.is_loading(level)
+        })
+        .map(|(level, active)| (level.clone(), active.shift))
+        .collect();
+    for (level, shift) in loading {
+        active_levels.set_state(&level, LevelState::Active, shift);
+    }
+
+    for transition in std::mem::take(&mut active_levels.transitions) {
+        match transition.to {
+            LevelState::Active if transition.from == LevelState::Loading => {
+                loaded.write(LevelLoaded {
+                    level: transition.level.clone(),
+                    shift: active_levels.levels[&transition.level].shift,
+                });
+            }
+            LevelState::Despawned => {
+                unloaded.write(LevelUnloaded {
+                    level: transition.level.clone(),
+                });
+            }
+            _ => {}
+        }
+        state_changed.write(transition);
+    }
+}
@@ -0,0 +1,425 @@
+//! Checks every level in the manifest for mistakes that would otherwise only show up at runtime.
+//!
+//! Run with `cargo run --bin level-lint`. Exits with a non-zero status if any problem is found.
+
+use std::{path::Path, process::ExitCode};
+
+use bevy::{
+    asset::RenderAssetUsages,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use bevy_github_ci_template::{
+    electricity::Channel,
+    level_manifest::{LevelEntry, LevelManifest},
+    palette::{Palette, Tile},
+    parsed_level::{HallwayError, ParsedLevel, is_electrical, pair_hallways},
+    switch::SwitchKind,
+};
+
+struct Problem {
+    level: String,
+    at: Option<IVec2>,
+    message: String,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.at {
+            Some(at) => write!(f, "{} ({}, {}): {}", self.level, at.x, at.y, self.message),
+            None => write!(f, "{}: {}", self.level, self.message),
+        }
+    }
+}
+
+fn read_ron<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+    ron::from_str(&text).map_err(|err| format!("could not parse {}: {err}", path.display()))
+}
+
+fn read_image(path: &Path) -> Result<Image, String> {
+    let image =
+        image::open(path).map_err(|err| format!("could not read {}: {err}", path.display()))?;
+    // Levels are loaded as linear, just like in the game.
+    Ok(Image::from_dynamic(
+        image,
+        false,
+        RenderAssetUsages::default(),
+    ))
+}
+
+fn main() -> ExitCode {
+    let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+
+    let manifest: LevelManifest = match read_ron(&assets.join("manifest.levels.ron")) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let palette: Palette = match read_ron(&assets.join("tiles.palette.ron")) {
+        Ok(palette) => palette,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut problems: Vec<Problem> = Vec::new();
+    if let Err(err) = manifest.validate() {
+        problems.push(Problem {
+            level: "manifest.levels.ron".to_string(),
+            at: None,
+            message: err.to_string(),
+        });
+    }
+    if let Err(err) = palette.validate() {
+        problems.push(Problem {
+            level: "tiles.palette.ron".to_string(),
+            at: None,
+            message: err.to_string(),
+        });
+    }
+
+    let mut parsed_levels: HashMap<String, ParsedLevel> = HashMap::new();
+    for entry in manifest.levels.iter() {
+        let image = match read_image(&assets.join(&entry.file)) {
+            Ok(image) => image,
+            Err(err) => {
+                problems.push(Problem {
+                    level: entry.file.clone(),
+                    at: None,
+                    message: err,
+                });
+                continue;
+            }
+        };
+
+        let parsed = ParsedLevel::from_image(&image, &palette);
+        lint_level(&entry.file, &parsed, &mut problems);
+        lint_tile_settings(entry, &parsed, &mut problems);
+
+        parsed_levels.insert(entry.file.clone(), parsed);
+    }
+
+    lint_links(&manifest, &parsed_levels, &mut problems);
+
+    if let Err(errors) = pair_hallways(parsed_levels.iter()) {
+        for err in errors {
+            let (level, center, message) = match err {
+                HallwayError::Unpaired { level, center } => (
+                    level,
+                    center,
+                    "hallway pattern does not match any other level",
+                ),
+                HallwayError::Ambiguous { levels, center } => (
+                    levels.join(", "),
+                    center,
+                    "hallway pattern is shared by more than two hallways",
+                ),
+            };
+            problems.push(Problem {
+                level,
+                at: Some(center.round().as_ivec2()),
+                message: message.to_string(),
+            });
+        }
+    }
+
+    // Problems are found in hash map order, so sort them to keep the output stable between runs.
+    problems.sort_by(|a, b| {
+        let key = |problem: &Problem| problem.at.map(|at| (at.y, at.x));
+        (&a.level, key(a), &a.message).cmp(&(&b.level, key(b), &b.message))
+    });
+    for problem in problems.iter() {
+        println!("{problem}");
+    }
+
+    if problems.is_empty() {
+        println!("{} levels OK", manifest.levels.len());
+        ExitCode::SUCCESS
+    } else {
+        println!("{} problems found", problems.len());
+        ExitCode::FAILURE
+    }
+}
+
+fn lint_level(level: &str, parsed: &ParsedLevel, problems: &mut Vec<Problem>) {
+    let mut problem = |at: IVec2, message: String| {
+        problems.push(Problem {
+            level: level.to_string(),
+            at: Some(at),
+            message,
+        });
+    };
+
+    let grid = &parsed.grid;
+    for (at, color) in grid.unknown.iter() {
+        let color = color.to_linear();
+        problem(
+            *at,
+            format!(
+                "unknown color ({}, {}, {})",
+                (color.red * 255.).round(),
+                (color.green * 255.).round(),
+                (color.blue * 255.).round(),
+            ),
+        );
+    }
+
+    for (&at, tile) in grid.tiles.iter() {
+        if is_electrical(tile) && grid.is_on_border(at) {
+            problem(
+                at,
+                format!("electrical tile {tile:?} touches the image border"),
+            );
+        }
+    }
+
+    for (&at, tile) in grid.tiles.iter() {
+        if !is_electrical(tile) {
+            continue;
+        }
+        for d in [IVec2::X, IVec2::Y] {
+            let (a, b) = (parsed.layers(at), parsed.layers(at + d));
+            if is_electrical(&parsed.tile(at + d)) && (a.end() < b.start() || b.end() < a.start()) {
+                problem(
+                    at,
+                    format!(
+                        "{tile:?} is at a different height than the {:?} next to it, so they \
+                         need a vertical wire to connect",
+                        parsed.tile(at + d)
+                    ),
+                );
+            }
+        }
+    }
+
+    for region in parsed.closed_ziplines.iter() {
+        problem(
+            region_corner(region),
+            "zipline has no endpoints".to_string(),
+        );
+    }
+
+    for (&at, sides) in parsed.gates.iter() {
+        if sides.outputs.len() != 1 {
+            problem(
+                at,
+                format!(
+                    "logic gate has {} gate outputs next to it, but must have exactly 1",
+                    sides.outputs.len()
+                ),
+            );
+        }
+        if sides.inputs.is_empty() {
+            problem(at, "logic gate has no inputs".to_string());
+        }
+    }
+
+    for (&at, tile) in grid.tiles.iter() {
+        if let Tile::Diode(direction) = tile {
+            let offset = direction.offset();
+            if !is_electrical(&parsed.tile(at - offset)) {
+                problem(
+                    at,
+                    "diode has nothing behind it to take charge from".to_string(),
+                );
+            }
+            if !is_electrical(&parsed.tile(at + offset)) {
+                problem(
+                    at,
+                    "diode has nothing in front of it to pass charge to".to_string(),
+                );
+            }
+        }
+    }
+
+    for (&at, tile) in grid.tiles.iter() {
+        // Only the tiles that take their channel from their neighbors.
+        let inferred = is_electrical(tile)
+            && !matches!(
+                tile,
+                Tile::FloorWire
+                    | Tile::CrossFloorWire
+                    | Tile::WallWire
+                    | Tile::ChannelWire(_)
+                    | Tile::ChannelCrossing
+                    | Tile::Gate(_)
+                    | Tile::Diode(_)
+            );
+        if !inferred {
+            continue;
+        }
+        let neighbor_channels: HashSet<Channel> = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+            .into_iter()
+            .filter_map(|d| match parsed.tile(at + d) {
+                Tile::ChannelWire(channel) => Some(channel),
+                _ => None,
+            })
+            .collect();
+        if neighbor_channels.len() > 1 {
+            problem(
+                at,
+                format!("{tile:?} touches wires of more than one channel, so it stays plain"),
+            );
+        }
+    }
+
+    for chain in parsed.chains.iter() {
+        if chain.ends.len() < 2 {
+            problem(
+                region_corner(&chain.links),
+                format!(
+                    "chain has {} ends, but must have at least 2",
+                    chain.ends.len()
+                ),
+            );
+        }
+    }
+}
+
+fn lint_tile_settings(entry: &LevelEntry, parsed: &ParsedLevel, problems: &mut Vec<Problem>) {
+    for settings in entry.tile_settings.iter() {
+        let at = IVec2::from(settings.position);
+        let tile = parsed.tile(at);
+        if settings.min_mass.is_some() && !matches!(tile, Tile::PressurePlate(_)) {
+            problems.push(Problem {
+                level: entry.file.clone(),
+                at: Some(at),
+                message: format!("`min_mass` is set on {tile:?}, which is not a pressure plate"),
+            });
+        }
+        if settings.duration.is_some() && tile != Tile::Switch(SwitchKind::Timed) {
+            problems.push(Problem {
+                level: entry.file.clone(),
+                at: Some(at),
+                message: format!("`duration` is set on {tile:?}, which has no duration"),
+            });
+        }
+        if settings
+            .duration
+            .is_some_and(|duration| !duration.is_finite() || duration <= 0.0)
+        {
+            problems.push(Problem {
+                level: entry.file.clone(),
+                at: Some(at),
+                message: "`duration` must be a positive number of seconds".to_string(),
+            });
+        }
+        if settings
+            .capacity
+            .is_some_and(|capacity| !capacity.is_finite() || capacity <= 0.0)
+        {
+            problems.push(Problem {
+                level: entry.file.clone(),
+                at: Some(at),
+                message: "`capacity` must be a positive number of seconds".to_string(),
+            });
+        }
+        if settings.capacity.is_some() && !matches!(tile, Tile::Capacitor | Tile::Battery) {
+            problems.push(Problem {
+                level: entry.file.clone(),
+                at: Some(at),
+                message: format!("`capacity` is set on {tile:?}, which stores no charge"),
+            });
+        }
+        if settings.length.is_some() && !matches!(tile, Tile::CableReel | Tile::Chain) {
+            problems.push(Problem {
+                level: entry.file.clone(),
+                at: Some(at),
+                message: format!("`length` is set on {tile:?}, which has no length"),
+            });
+        }
+        let is_wireless = matches!(tile, Tile::Transmitter | Tile::Receiver);
+        if settings.link.is_some() && !is_wireless {
+            problems.push(Problem {
+                level: entry.file.clone(),
+                at: Some(at),
+                message: format!("`link` is set on {tile:?}, which is not wireless"),
+            });
+        }
+    }
+
+    for (&at, &tile) in parsed.grid.tiles.iter() {
+        if !matches!(tile, Tile::Transmitter | Tile::Receiver) {
+            continue;
+        }
+        let has_link = entry
+            .tile_settings
+            .iter()
+            .any(|settings| IVec2::from(settings.position) == at && settings.link.is_some());
+        if !has_link {
+            problems.push(Problem {
+                level: entry.file.clone(),
+                at: Some(at),
+                message: format!("{tile:?} has no `link` in its tile settings"),
+            });
+        }
+    }
+}
+
+/// Checks that every link has exactly one transmitter and at least one receiver, in any level.
+fn lint_links(
+    manifest: &LevelManifest,
+    parsed_levels: &HashMap<String, ParsedLevel>,
+    problems: &mut Vec<Problem>,
+) {
+    let mut transmitters: HashMap<&str, Vec<(&str, IVec2)>> = HashMap::new();
+    let mut receivers: HashMap<&str, Vec<(&str, IVec2)>> = HashMap::new();
+    for entry in manifest.levels.iter() {
+        let Some(parsed) = parsed_levels.get(&entry.file) else {
+            continue;
+        };
+        for settings in entry.tile_settings.iter() {
+            let Some(link) = settings.link.as_deref() else {
+                continue;
+            };
+            let at = IVec2::from(settings.position);
+            let ends = match parsed.tile(at) {
+                Tile::Transmitter => &mut transmitters,
+                Tile::Receiver => &mut receivers,
+                _ => continue,
+            };
+            ends.entry(link).or_default().push((&entry.file, at));
+        }
+    }
+
+    for (link, ends) in transmitters.iter() {
+        if ends.len() > 1 {
+            for &(level, at) in ends.iter() {
+                problems.push(Problem {
+                    level: level.to_string(),
+                    at: Some(at),
+                    message: format!("link {link:?} has more than one transmitter"),
+                });
+            }
+        }
+        if !receivers.contains_key(link) {
+            for &(level, at) in ends.iter() {
+                problems.push(Problem {
+                    level: level.to_string(),
+                    at: Some(at),
+                    message: format!("link {link:?} has no receivers"),
+                });
+            }
+        }
+    }
+    for (link, ends) in receivers.iter() {
+        if !transmitters.contains_key(link) {
+            for &(level, at) in ends.iter() {
+                problems.push(Problem {
+                    level: level.to_string(),
+                    at: Some(at),
+                    message: format!("link {link:?} has no transmitter"),
+                });
+            }
+        }
+    }
+}
+
+fn region_corner(region: &[IVec2]) -> IVec2 {
+    region.iter().copied().min_by_key(|p| (p.y, p.x)).unwrap()
+}
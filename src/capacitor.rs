@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+
+use crate::{
+    common::Common,
+    draggable::Draggable,
+    electricity::{Channel, Plug, PowerPart, compute_charge_system, sync_power_network_system},
+    power_network::{PowerNetwork, PowerRole},
+};
+
+/// How many seconds a capacitor can power its net for, unless the level's tile settings say
+/// otherwise.
+pub const DEFAULT_CAPACITY: f32 = 10.0;
+
+/// Seconds of stored power gained for each second spent connected to a source.
+const CHARGE_RATE: f32 = 2.0;
+
+/// Stores charge while its net is powered, and keeps powering the net once the power is gone.
+#[derive(Component)]
+pub struct Capacitor {
+    /// How many more seconds the capacitor can power its net for.
+    pub charge: f32,
+    pub capacity: f32,
+}
+
+impl Capacitor {
+    pub fn empty(capacity: f32) -> Self {
+        Self {
+            charge: 0.0,
+            capacity,
+        }
+    }
+
+    pub fn full(capacity: f32) -> Self {
+        Self {
+            charge: capacity,
+            capacity,
+        }
+    }
+}
+
+/// A capacitor that the player can carry and plug into outlets. It is a [`Plug`] without a
+/// cable, so it is its own cable, and it can't be joined to other plugs.
+#[derive(Component)]
+#[require(Draggable)]
+pub struct Battery;
+
+/// A bar that fills up with the charge of the capacitor it is a child of.
+#[derive(Component)]
+pub struct ChargeLevel;
+
+pub struct CapacitorPlugin;
+
+impl Plugin for CapacitorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            // Capacitors pick their role from the freshly synced nets, so that they take over
+            // in the same tick that their source is cut off, before the net can lose power.
+            (attach_battery_system, charge_capacitor_system)
+                .chain()
+                .after(sync_power_network_system)
+                .before(compute_charge_system),
+        )
+        .add_systems(Update, charge_level_system);
+    }
+}
+
+/// Adds batteries to the net of the outlet they are plugged into, and takes them out again once
+/// they are unplugged.
+fn attach_battery_system(
+    mut network: ResMut<PowerNetwork>,
+    batteries: Query<(Entity, &Plug, &Capacitor), With<Battery>>,
+) {
+    for (entity, plug, capacitor) in batteries.iter() {
+        let outlet_node = plug
+            .outlet
+            .and_then(|outlet| network.node_of(outlet))
+            .cloned();
+        if network.node_of(entity) == outlet_node.as_ref() {
+            continue;
+        }
+        network.remove(entity);
+        if let Some(node) = outlet_node {
+            let role = if capacitor.charge > 0.0 {
+                PowerRole::Battery
+            } else {
+                PowerRole::Conductor
+            };
+            network.add(entity, node, role);
+        }
+    }
+}
+
+fn charge_capacitor_system(
+    time: Res<Time>,
+    mut network: ResMut<PowerNetwork>,
+    mut capacitors: Query<(Entity, &mut Capacitor, Option<&mut PowerPart>)>,
+) {
+    for (entity, mut capacitor, part) in capacitors.iter_mut() {
+        if network.node_of(entity).is_none() {
+            // Unplugged batteries keep their charge.
+            continue;
+        }
+
+        let role = if network.is_charging(entity) {
+            capacitor.charge =
+                (capacitor.charge + time.delta_secs() * CHARGE_RATE).min(capacitor.capacity);
+            PowerRole::Conductor
+        } else if capacitor.charge > 0.0 {
+            capacitor.charge = (capacitor.charge - time.delta_secs()).max(0.0);
+            PowerRole::Battery
+        } else {
+            PowerRole::Conductor
+        };
+
+        network.set_role(entity, role);
+        if let Some(mut part) = part
+            && part.role != role
+        {
+            part.role = role;
+        }
+    }
+}
+
+fn charge_level_system(
+    common: Res<Common>,
+    capacitors: Query<(&Capacitor, &Channel)>,
+    mut levels: Query<
+        (
+            &ChildOf,
+            &mut Transform,
+            &mut MeshMaterial3d<StandardMaterial>,
+        ),
+        With<ChargeLevel>,
+    >,
+) {
+    for (child_of, mut transform, mut material) in levels.iter_mut() {
+        let Ok((capacitor, channel)) = capacitors.get(child_of.parent()) else {
+            continue;
+        };
+        let fill = capacitor.charge / capacitor.capacity;
+        // The bar grows up from the bottom of the capacitor.
+        transform.scale.y = fill;
+        transform.translation.y = (fill - 1.0) / 2.0;
+        let expected_material = common.electricity_material(*channel);
+        if &material.0 != expected_material {
+            material.0 = expected_material.clone();
+        }
+    }
+}
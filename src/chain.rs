@@ -1,13 +1,46 @@
-use bevy::prelude::*;
+use std::time::Duration;
+
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{
+    common::Common,
+    electricity::{Plug, PlugPopped},
+    email_spawner::Particle,
+};
 
 #[derive(Component)]
 pub struct ChainLink(pub Entity, pub Entity);
 
+/// How far apart the two ends of a [`ChainLink`] are when it is slack.
+#[derive(Component)]
+pub struct LinkLength(pub f32);
+
+impl LinkLength {
+    /// How far the link's ends are pulled past its length, or zero if it is slack.
+    pub fn stretch(&self, a: Vec3, b: Vec3) -> f32 {
+        (a.distance(b) - self.0).max(0.0)
+    }
+}
+
+/// Lets a chain snap apart at this link once its ends are pulled more than `max_length` apart.
+#[derive(Component)]
+pub struct ChainSnap {
+    pub max_length: f32,
+}
+
+/// Sent when a chain snaps apart.
+#[derive(Event, Clone, Debug)]
+pub struct ChainSnapped {
+    pub position: Vec3,
+}
+
 pub struct ChainPlugin;
 
 impl Plugin for ChainPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, draw_chains_system);
+        app.add_event::<ChainSnapped>()
+            .add_systems(FixedUpdate, snap_chains_system)
+            .add_systems(Update, (draw_chains_system, cable_cue_system));
     }
 }
 
@@ -30,3 +63,109 @@ fn draw_chains_system(
             .with_scale(Vec3::new(0.2, 0.2, a.distance(b) + 0.2));
     }
 }
+
+/// Despawns the joint of any link that has been stretched past its limit, and splits the cable it
+/// was part of in two.
+fn snap_chains_system(
+    mut commands: Commands,
+    mut snapped: EventWriter<ChainSnapped>,
+    transform: Query<&Transform, Without<ChainLink>>,
+    links: Query<(Entity, &ChainLink, Option<&ChainSnap>)>,
+    mut plugs: Query<(Entity, &mut Plug)>,
+) {
+    let mut broken: Vec<Entity> = Vec::new();
+    for (entity, link, snap) in links.iter() {
+        let Some(snap) = snap else {
+            continue;
+        };
+        let (Ok(a), Ok(b)) = (transform.get(link.0), transform.get(link.1)) else {
+            continue;
+        };
+        if a.translation.distance(b.translation) > snap.max_length {
+            commands.entity(entity).despawn();
+            snapped.write(ChainSnapped {
+                position: (a.translation + b.translation) / 2.0,
+            });
+            broken.push(entity);
+        }
+    }
+
+    if !broken.is_empty() {
+        split_cables(&links, &broken, &mut plugs);
+    }
+}
+
+/// Gives the plugs on each piece of a snapped cable a cable of their own, so that the pieces no
+/// longer carry power between each other. The piece with the cable's original plug keeps it.
+fn split_cables(
+    links: &Query<(Entity, &ChainLink, Option<&ChainSnap>)>,
+    broken: &[Entity],
+    plugs: &mut Query<(Entity, &mut Plug)>,
+) {
+    // Group the bodies that are still linked together.
+    let mut parents: HashMap<Entity, Entity> = HashMap::new();
+    fn root(parents: &HashMap<Entity, Entity>, mut body: Entity) -> Entity {
+        while let Some(&parent) = parents.get(&body) {
+            body = parent;
+        }
+        body
+    }
+    for (entity, link, _) in links.iter() {
+        if broken.contains(&entity) {
+            continue;
+        }
+        let (a, b) = (root(&parents, link.0), root(&parents, link.1));
+        if a != b {
+            parents.insert(a.max(b), a.min(b));
+        }
+    }
+
+    let mut pieces: HashMap<(Entity, Entity), Entity> = HashMap::new();
+    for (entity, plug) in plugs.iter() {
+        let piece = pieces
+            .entry((plug.cable, root(&parents, entity)))
+            .or_insert(entity);
+        if entity == plug.cable || (*piece != plug.cable && entity < *piece) {
+            *piece = entity;
+        }
+    }
+    for (entity, mut plug) in plugs.iter_mut() {
+        let cable = pieces[&(plug.cable, root(&parents, entity))];
+        if plug.cable != cable {
+            plug.cable = cable;
+        }
+    }
+}
+
+/// Throws sparks and plays a short tone whenever a plug pops out or a chain snaps.
+fn cable_cue_system(
+    mut commands: Commands,
+    common: Res<Common>,
+    mut pitches: ResMut<Assets<Pitch>>,
+    mut popped: EventReader<PlugPopped>,
+    mut snapped: EventReader<ChainSnapped>,
+    transform: Query<&Transform>,
+) {
+    let positions: Vec<(Vec3, f32)> = popped
+        .read()
+        .filter_map(|event| transform.get(event.plug).ok())
+        .map(|plug| (plug.translation, 880.0))
+        .chain(snapped.read().map(|event| (event.position, 220.0)))
+        .collect();
+
+    for (position, frequency) in positions {
+        for i in 0..8 {
+            let angle = i as f32 * std::f32::consts::TAU / 8.0;
+            commands.spawn((
+                Particle::new(0.4, Vec3::new(angle.cos() * 3.0, 4.0, angle.sin() * 3.0)),
+                Mesh3d(common.mesh_small_sphere.clone()),
+                MeshMaterial3d(common.material_electricity.clone()),
+                Transform::from_translation(position).with_scale(Vec3::splat(0.3)),
+            ));
+        }
+        commands.spawn((
+            AudioPlayer(pitches.add(Pitch::new(frequency, Duration::from_millis(120)))),
+            PlaybackSettings::DESPAWN,
+        ));
+    }
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
-#[derive(Resource)]
+use crate::electricity::Channel;
+
+#[derive(Resource, Clone)]
 pub struct Common {
     pub mesh_cube: Handle<Mesh>,
     pub mesh_ruby: Handle<Mesh>,
@@ -17,7 +19,12 @@ pub struct Common {
     pub material_beepboop: Handle<StandardMaterial>,
     pub material_active: Handle<StandardMaterial>,
     pub material_electricity: Handle<StandardMaterial>,
+    pub material_electricity_red: Handle<StandardMaterial>,
+    pub material_electricity_green: Handle<StandardMaterial>,
+    pub material_electricity_blue: Handle<StandardMaterial>,
     pub material_laser: Handle<StandardMaterial>,
+    pub material_mirror: Handle<StandardMaterial>,
+    pub material_splitter: Handle<StandardMaterial>,
     pub material_invisible: Handle<StandardMaterial>,
     pub material_fog: Handle<StandardMaterial>,
     pub material_outlet: Handle<StandardMaterial>,
@@ -34,14 +41,22 @@ pub struct Common {
     pub scene_computer: Handle<Scene>,
     pub scene_ruby: Handle<Scene>,
 
-    pub material_tutorial_move: Handle<StandardMaterial>,
-    pub material_tutorial_interact: Handle<StandardMaterial>,
-    pub material_tutorial_zip: Handle<StandardMaterial>,
-
     pub material_you_win: Handle<StandardMaterial>,
     pub material_email: Handle<StandardMaterial>,
 }
 
+impl Common {
+    /// The material of powered wires on the channel.
+    pub fn electricity_material(&self, channel: Channel) -> &Handle<StandardMaterial> {
+        match channel {
+            Channel::Plain => &self.material_electricity,
+            Channel::Red => &self.material_electricity_red,
+            Channel::Green => &self.material_electricity_green,
+            Channel::Blue => &self.material_electricity_blue,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct CommonPlugin;
 
@@ -122,12 +137,42 @@ pub fn setup_common(
             perceptual_roughness: 1.0,
             ..default()
         }),
+        material_electricity_red: materials.add(StandardMaterial {
+            base_color: Color::linear_rgb(1.0, 0.3, 0.2),
+            emissive: LinearRgba::rgb(1.0, 0.2, 0.1) * 20.,
+            perceptual_roughness: 1.0,
+            ..default()
+        }),
+        material_electricity_green: materials.add(StandardMaterial {
+            base_color: Color::linear_rgb(0.3, 1.0, 0.3),
+            emissive: LinearRgba::rgb(0.2, 1.0, 0.2) * 20.,
+            perceptual_roughness: 1.0,
+            ..default()
+        }),
+        material_electricity_blue: materials.add(StandardMaterial {
+            base_color: Color::linear_rgb(0.2, 0.3, 1.0),
+            emissive: LinearRgba::rgb(0.1, 0.2, 1.0) * 20.,
+            perceptual_roughness: 1.0,
+            ..default()
+        }),
         material_laser: materials.add(StandardMaterial {
             base_color: Color::linear_rgb(1.0, 0.0, 0.3),
             emissive: LinearRgba::rgb(1.0, 0.2, 0.3) * 20.,
             perceptual_roughness: 1.0,
             ..default()
         }),
+        material_mirror: materials.add(StandardMaterial {
+            base_color: Color::linear_rgb(0.8, 0.85, 0.9),
+            perceptual_roughness: 0.05,
+            metallic: 1.0,
+            ..default()
+        }),
+        material_splitter: materials.add(StandardMaterial {
+            base_color: Color::linear_rgba(0.6, 0.8, 0.9, 0.5),
+            alpha_mode: AlphaMode::Blend,
+            perceptual_roughness: 0.05,
+            ..default()
+        }),
         material_zappy_boy: materials.add(StandardMaterial {
             base_color: Color::linear_rgb(0.8, 0.88, 0.4),
             emissive: LinearRgba::rgb(1.0, 0.2, 0.3) * 20.,
@@ -181,24 +226,6 @@ pub fn setup_common(
         scene_computer: asset_server.load("computer_console.glb#Scene0"),
         scene_ruby: asset_server.load("ruby.glb#Scene0"),
 
-        material_tutorial_move: materials.add(StandardMaterial {
-            base_color_texture: Some(asset_server.load("tutorial_move.png")),
-            perceptual_roughness: 1.0,
-            ..default()
-        }),
-
-        material_tutorial_interact: materials.add(StandardMaterial {
-            base_color_texture: Some(asset_server.load("tutorial_interact.png")),
-            perceptual_roughness: 1.0,
-            ..default()
-        }),
-
-        material_tutorial_zip: materials.add(StandardMaterial {
-            base_color_texture: Some(asset_server.load("tutorial_zip.png")),
-            perceptual_roughness: 1.0,
-            ..default()
-        }),
-
         material_you_win: materials.add(StandardMaterial {
             base_color_texture: Some(asset_server.load("win_message.png")),
             perceptual_roughness: 1.0,
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::electricity::PowerGrid;
+use crate::electricity::Powered;
 
 #[derive(Component)]
 pub struct Door {
@@ -19,11 +19,8 @@ impl Plugin for DoorPlugin {
     }
 }
 
-fn door_open_system(mut doors: Query<(&mut Transform, &mut Door)>, power_grid: Res<PowerGrid>) {
-    for (mut door_transform, door) in doors.iter_mut() {
-        let grid = door_transform.translation.xz().round().as_ivec2();
-        let is_open = power_grid.active.contains(&grid);
-
+fn door_open_system(mut doors: Query<(&mut Transform, &Door, Has<Powered>)>) {
+    for (mut door_transform, door, is_open) in doors.iter_mut() {
         let target_translation = if is_open {
             door.open_at
         } else {
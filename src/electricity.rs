@@ -1,49 +1,102 @@
 use avian3d::prelude::*;
-use bevy::{
-    platform::collections::{HashMap, HashSet},
-    prelude::*,
-};
+use bevy::{platform::collections::HashMap, prelude::*};
+use serde::Deserialize;
 
 use crate::{
+    active_levels::{LevelLoaded, LevelUnloaded},
+    capacitor::Battery,
+    chain::{ChainLink, LinkLength},
     common::Common,
     draggable::Draggable,
     interactible::{Activated, Interactible},
+    level::LevelTag,
     mainframe::Mainframe,
+    power_network::{GateKind, NodeId, PowerNetwork, PowerRole},
 };
 
 pub struct ElectricityPlugin;
 
 impl Plugin for ElectricityPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(PowerGrid {
-            active: HashSet::new(),
-        });
+        app.init_resource::<PowerNetwork>()
+            .add_event::<PowerGained>()
+            .add_event::<PowerLost>()
+            .add_event::<PlugPopped>();
         app.add_systems(
             FixedUpdate,
             (
                 outlet_interactive_system.after(crate::draggable::run_draggable_system),
+                join_plugs_system,
                 plug_physics_system.after(crate::draggable::run_draggable_system),
+                sync_power_network_system,
                 compute_charge_system,
                 visible_wire_system,
+                visible_gate_system,
             )
                 .chain(),
         );
     }
 }
 
-/// This component currently has electricity.
+/// This entity currently has electricity. Inserted and removed by [`compute_charge_system`].
 #[derive(Component)]
-pub struct Charged;
+pub struct Powered;
+
+/// Sent when a cable is pulled so hard that a plug pops out of its outlet.
+#[derive(Event, Clone, Debug)]
+pub struct PlugPopped {
+    pub plug: Entity,
+    pub outlet: Entity,
+}
+
+/// Sent when an entity becomes [`Powered`].
+#[derive(Event, Clone, Debug)]
+pub struct PowerGained {
+    pub entity: Entity,
+}
+
+/// Sent when an entity stops being [`Powered`].
+#[derive(Event, Clone, Debug)]
+pub struct PowerLost {
+    pub entity: Entity,
+}
+
+/// How far a cable reel's cord reaches, unless the level's tile settings say otherwise.
+pub const DEFAULT_REEL_LENGTH: f32 = 8.0;
+
+/// How close a dropped plug must be to another cable's plug to join it.
+const JOIN_RADIUS: f32 = 1.0;
+
+/// How strongly an outlet pulls on its plug, per unit of distance.
+const PLUG_STIFFNESS: f32 = 20.0;
+
+/// How far past its length a link of the cable next to a plug can be pulled before the plug
+/// pops out of its outlet. Links stretch more the harder the cable is pulled.
+const MAX_PLUG_STRETCH: f32 = 0.5;
 
 #[derive(Component)]
 #[require(ExternalForce)]
 pub struct Plug {
     pub outlet: Option<Entity>,
-    pub other_end: Entity,
+    /// The cable the plug is on. Every plug of a cable has the same `cable`, which is one of the
+    /// cable's own plugs.
+    pub cable: Entity,
+    /// The plug of another cable that this one is joined to, end to end.
+    pub joined: Option<Entity>,
+}
+
+impl Plug {
+    pub fn new(cable: Entity) -> Self {
+        Self {
+            outlet: None,
+            cable,
+            joined: None,
+        }
+    }
 }
 
 #[derive(Component, Default)]
-#[require(ExternalForce)]
+#[require(ExternalForce, PowerPart = PowerPart::new(PowerRole::Conductor))]
 pub struct Outlet {
     pub plug: Option<Entity>,
 }
@@ -76,11 +129,11 @@ pub fn outlet_interactive_system(
     if let Some(mut dragging_plug) = dragging_plug {
         // Enable all outlets without plugs
         for outlet in outlets_without_interactible.iter() {
-            if let Ok(outlet) = activate_outlet.get(outlet) {
-                if outlet.2.plug.is_some() {
-                    // Already has a plug, so it not interactive.
-                    continue;
-                }
+            if let Ok(outlet) = activate_outlet.get(outlet)
+                && outlet.2.plug.is_some()
+            {
+                // Already has a plug, so it not interactive.
+                continue;
             }
             commands
                 .entity(outlet)
@@ -105,11 +158,107 @@ pub fn outlet_interactive_system(
     }
 }
 
+/// Joins a dropped plug to the nearest free plug of another cable, and separates joined plugs
+/// once either of them is picked up again, or either of them is despawned.
+fn join_plugs_system(
+    dropped: Query<(Entity, &Draggable), (Changed<Draggable>, Without<Battery>)>,
+    mut plugs: Query<(Entity, &Transform, &mut Plug, &Draggable), Without<Battery>>,
+) {
+    let dangling: Vec<Entity> = plugs
+        .iter()
+        .filter(|(_, _, plug, _)| plug.joined.is_some_and(|joined| !plugs.contains(joined)))
+        .map(|(entity, _, _, _)| entity)
+        .collect();
+    for entity in dangling {
+        plugs.get_mut(entity).unwrap().2.joined = None;
+    }
+
+    for (entity, draggable) in dropped.iter() {
+        let Ok((_, transform, plug, _)) = plugs.get(entity) else {
+            continue;
+        };
+        if draggable.is_dragging {
+            if let Some(joined) = plug.joined {
+                plugs.get_mut(entity).unwrap().2.joined = None;
+                if let Ok((_, _, mut other, _)) = plugs.get_mut(joined) {
+                    other.joined = None;
+                }
+            }
+            continue;
+        }
+        if plug.outlet.is_some() || plug.joined.is_some() {
+            continue;
+        }
+
+        let position = transform.translation;
+        let cable = plug.cable;
+        let nearest = plugs
+            .iter()
+            .filter(|(_, _, other, other_draggable)| {
+                other.cable != cable
+                    && other.outlet.is_none()
+                    && other.joined.is_none()
+                    && !other_draggable.is_dragging
+            })
+            .map(|(other, other_transform, _, _)| {
+                (other, other_transform.translation.distance(position))
+            })
+            .filter(|&(_, distance)| distance < JOIN_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((other, _)) = nearest {
+            plugs.get_mut(entity).unwrap().2.joined = Some(other);
+            plugs.get_mut(other).unwrap().2.joined = Some(entity);
+        }
+    }
+}
+
 fn plug_physics_system(
+    mut popped: EventWriter<PlugPopped>,
     transform: Query<&Transform>,
-    mut plug: Query<(&Transform, &LinearVelocity, &mut ExternalForce, &Plug)>,
+    links: Query<(&ChainLink, &LinkLength)>,
+    mut outlets: Query<&mut Outlet>,
+    mut plug: Query<(
+        Entity,
+        &Transform,
+        &LinearVelocity,
+        &mut ExternalForce,
+        &mut Plug,
+    )>,
 ) {
-    for (plug_transform, plug_velocity, mut plug_force, plug) in plug.iter_mut() {
+    // The links next to a plug carry the whole pull on the cable, so find how far the links next
+    // to each plugged-in plug are stretched, going through the links once.
+    let mut stretches: HashMap<Entity, f32> = plug
+        .iter()
+        .filter(|(_, _, _, _, plug)| plug.outlet.is_some())
+        .map(|(entity, ..)| (entity, 0.0))
+        .collect();
+    for (link, length) in links.iter() {
+        if !stretches.contains_key(&link.0) && !stretches.contains_key(&link.1) {
+            continue;
+        }
+        let (Ok(a), Ok(b)) = (transform.get(link.0), transform.get(link.1)) else {
+            continue;
+        };
+        let stretch = length.stretch(a.translation, b.translation);
+        for end in [link.0, link.1] {
+            if let Some(most) = stretches.get_mut(&end) {
+                *most = most.max(stretch);
+            }
+        }
+    }
+
+    for (entity, plug_transform, plug_velocity, mut plug_force, mut plug) in plug.iter_mut() {
+        if let Some(joined) = plug.joined {
+            plug_force.clear();
+
+            // Joined plugs pull on each other, so they stay together.
+            let Ok(target) = transform.get(joined) else {
+                continue;
+            };
+            let delta = target.translation - plug_transform.translation;
+
+            plug_force.set_force(delta * PLUG_STIFFNESS - plug_velocity.0 * 0.5);
+        }
         if let Some(outlet_entity) = plug.outlet {
             plug_force.clear();
 
@@ -120,121 +269,365 @@ fn plug_physics_system(
 
             let delta = target - plug_transform.translation;
 
-            plug_force.set_force(delta * 20. - plug_velocity.0 * 0.5);
+            if stretches
+                .get(&entity)
+                .is_some_and(|&stretch| stretch > MAX_PLUG_STRETCH)
+            {
+                plug.outlet = None;
+                if let Ok(mut outlet) = outlets.get_mut(outlet_entity) {
+                    outlet.plug = None;
+                }
+                popped.write(PlugPopped {
+                    plug: entity,
+                    outlet: outlet_entity,
+                });
+                continue;
+            }
+
+            plug_force.set_force(delta * PLUG_STIFFNESS - plug_velocity.0 * 0.5);
         }
     }
 }
 
+/// Which circuit a part belongs to. Parts on different channels never connect, even when they
+/// touch.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Hash, Debug, Default, Deserialize)]
+pub enum Channel {
+    /// The channel of ordinary wires.
+    #[default]
+    Plain,
+    Red,
+    Green,
+    Blue,
+}
+
+/// How many blocks above the ground a part sits. Parts at different heights only connect through
+/// vertical wires.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct GridLayer(pub i32);
+
+/// How an entity takes part in the [`PowerNetwork`]. Changing the role updates the network.
+#[derive(Component, Copy, Clone, Debug)]
+#[require(Channel, GridLayer)]
+pub struct PowerPart {
+    pub role: PowerRole,
+}
+
+impl PowerPart {
+    pub fn new(role: PowerRole) -> Self {
+        Self { role }
+    }
+}
+
 #[derive(Component)]
+#[require(PowerPart = PowerPart::new(PowerRole::Source))]
 pub struct PowerSource;
 
 #[derive(Component)]
+#[require(PowerPart = PowerPart::new(PowerRole::Conductor))]
 pub struct Wire;
 
-#[derive(Resource)]
-/// It's a pun, get it?
-pub struct PowerGrid {
-    pub active: HashSet<IVec2>,
+/// Which way a diode passes charge, as seen in the level's map image.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum DiodeDirection {
+    Right,
+    Left,
+    Down,
+    Up,
 }
 
-pub fn global_to_grid(p: Vec3) -> IVec2 {
-    p.round().xz().as_ivec2()
+impl DiodeDirection {
+    /// The grid direction that charge flows in.
+    pub fn offset(self) -> IVec2 {
+        match self {
+            Self::Right => IVec2::X,
+            Self::Left => IVec2::NEG_X,
+            Self::Down => IVec2::Y,
+            Self::Up => IVec2::NEG_Y,
+        }
+    }
 }
 
-pub fn compute_charge_system(
-    mut power_grid: ResMut<PowerGrid>,
-    sources: Query<(&GlobalTransform, &PowerSource)>,
-    outlets: Query<&GlobalTransform, With<Outlet>>,
-    wires: Query<&GlobalTransform, With<Wire>>,
-    mut mainframes: Query<(&GlobalTransform, &mut Mainframe)>,
-    plugs: Query<&Plug>,
-) {
-    power_grid.active.clear();
+/// A one-way wire. It is spawned with a single-input OR [`LogicGate`], which passes charge from
+/// behind it to the wire in front of it, and never back.
+#[derive(Component)]
+pub struct Diode {
+    pub direction: DiodeDirection,
+}
 
-    for (transform, _source) in sources.iter() {
-        power_grid
-            .active
-            .insert(global_to_grid(transform.translation()));
-    }
+/// A logic gate, which powers the wire on its output side depending on its input sides.
+#[derive(Component)]
+#[require(GridLayer)]
+pub struct LogicGate {
+    pub kind: GateKind,
+    /// The wires the gate reads.
+    pub inputs: Vec<GatePin>,
+    /// The wire the gate drives.
+    pub output: GatePin,
+}
 
-    let mut mainframes_to_charge: HashMap<IVec2, Mut<Mainframe>> = HashMap::new();
+/// One side of a [`LogicGate`]. Gates can read and drive any channel.
+#[derive(Copy, Clone, Debug)]
+pub struct GatePin {
+    /// The direction from the gate to the wire.
+    pub direction: IVec2,
+    pub channel: Channel,
+}
 
-    for (transform, mut mainframe) in mainframes.iter_mut() {
-        if !mainframe.active {
-            mainframe.has_charge = false;
-            mainframes_to_charge.insert(global_to_grid(transform.translation()), mainframe);
-            continue;
+/// The cell of the [`PowerNetwork`] that a part at `p` on the given layer belongs to.
+pub fn global_to_grid(p: Vec3, layer: GridLayer) -> IVec3 {
+    let p = p.round().as_ivec3();
+    IVec3::new(p.x, layer.0, p.z)
+}
+
+/// Keeps the [`PowerNetwork`] in sync with the loaded levels, cables and mainframes, and
+/// recomputes its nets. Nothing is told about the change until [`compute_charge_system`], so
+/// systems in between can react to the new nets first.
+pub fn sync_power_network_system(
+    mut network: ResMut<PowerNetwork>,
+    mut loaded: EventReader<LevelLoaded>,
+    mut unloaded: EventReader<LevelUnloaded>,
+    parts: Query<(
+        Entity,
+        &Transform,
+        &LevelTag,
+        Ref<PowerPart>,
+        &Channel,
+        &GridLayer,
+    )>,
+    gates: Query<(Entity, &Transform, &LevelTag, &LogicGate, &GridLayer)>,
+    plugs: Query<(Entity, &Plug)>,
+) {
+    for event in unloaded.read() {
+        network.remove_level(&event.level);
+    }
+
+    for event in loaded.read() {
+        for (entity, transform, level_tag, part, channel, layer) in parts.iter() {
+            if level_tag.level != event.level {
+                continue;
+            }
+            let node = NodeId {
+                level: event.level.clone(),
+                cell: global_to_grid(transform.translation, *layer),
+                channel: *channel,
+            };
+            network.add(entity, node, part.role);
         }
-        power_grid
-            .active
-            .insert(global_to_grid(transform.translation()));
-    }
-
-    let mut wire_grid: HashSet<IVec2> = HashSet::new();
-    for wire_transform in wires.iter() {
-        wire_grid.insert(global_to_grid(wire_transform.translation()));
-    }
-    for outlet_transform in outlets.iter() {
-        wire_grid.insert(global_to_grid(outlet_transform.translation()));
-    }
-
-    let mut plug_connections: HashMap<IVec2, IVec2> = HashMap::new();
-    for plug in plugs.iter() {
-        if let Ok(plug_other) = plugs.get(plug.other_end) {
-            // If both ends are plugged in, connect them in the grid.
-            if let Some(outlet) = plug.outlet {
-                if let Some(outlet_other) = plug_other.outlet {
-                    if let Ok(outlet_transform) = outlets.get(outlet) {
-                        if let Ok(other_outlet_transform) = outlets.get(outlet_other) {
-                            plug_connections.insert(
-                                global_to_grid(outlet_transform.translation()),
-                                global_to_grid(other_outlet_transform.translation()),
-                            );
-                        }
-                    }
-                }
+        for (entity, transform, level_tag, gate, layer) in gates.iter() {
+            if level_tag.level != event.level {
+                continue;
             }
+            let cell = global_to_grid(transform.translation, *layer);
+            let node = |pin: &GatePin| NodeId {
+                level: event.level.clone(),
+                cell: cell + IVec3::new(pin.direction.x, 0, pin.direction.y),
+                channel: pin.channel,
+            };
+            network.add_gate(
+                entity,
+                gate.kind,
+                // A gate sits on the channel of the wire it drives.
+                NodeId {
+                    level: event.level.clone(),
+                    cell,
+                    channel: gate.output.channel,
+                },
+                gate.inputs.iter().map(node).collect(),
+                node(&gate.output),
+            );
         }
     }
 
-    let mut stack: Vec<IVec2> = power_grid.active.iter().copied().collect();
-    while let Some(p) = stack.pop() {
-        // Expand charge to neighboring cells.
-        for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
-            let neighbor = p + IVec2::new(dx, dz);
-            if let Some(mainframe) = mainframes_to_charge.get_mut(&neighbor) {
-                mainframe.has_charge = true;
-            }
-            if wire_grid.contains(&neighbor) && !power_grid.active.contains(&neighbor) {
-                power_grid.active.insert(neighbor);
-                stack.push(neighbor);
+    for (entity, _, _, part, _, _) in parts.iter() {
+        if part.is_changed() {
+            network.set_role(entity, part.role);
+        }
+    }
+
+    let plugs: Vec<_> = plugs
+        .iter()
+        .map(|(entity, plug)| (entity, plug.cable, plug.joined, plug.outlet))
+        .collect();
+    network.set_cables(cable_links(&plugs));
+    network.recompute();
+}
+
+/// Applies any roles that changed since the [`PowerNetwork`] was synced, and updates the charge
+/// of anything affected by the changes.
+pub fn compute_charge_system(
+    mut commands: Commands,
+    mut network: ResMut<PowerNetwork>,
+    mut gained: EventWriter<PowerGained>,
+    mut lost: EventWriter<PowerLost>,
+    parts: Query<(Entity, Ref<PowerPart>)>,
+    mut mainframes: Query<(Entity, &mut Mainframe)>,
+    powered: Query<Entity, With<Powered>>,
+) {
+    for (entity, part) in parts.iter() {
+        if part.is_changed() {
+            network.set_role(entity, part.role);
+        }
+    }
+
+    network.recompute();
+    if network.take_changed() {
+        for entity in powered.iter() {
+            if !network.is_powered(entity) {
+                commands.entity(entity).remove::<Powered>();
+                lost.write(PowerLost { entity });
             }
         }
-        // Expand charge to cable-connected cells.
-        if let Some(other) = plug_connections.get(&p) {
-            if !power_grid.active.contains(other) {
-                power_grid.active.insert(*other);
-                stack.push(*other);
+        for entity in network.entities() {
+            if network.is_powered(entity) && !powered.contains(entity) {
+                commands.entity(entity).try_insert(Powered);
+                gained.write(PowerGained { entity });
             }
         }
     }
+
+    for (entity, mut mainframe) in mainframes.iter_mut() {
+        let has_charge = network.is_powered(entity);
+        if !mainframe.active && mainframe.has_charge != has_charge {
+            mainframe.has_charge = has_charge;
+        }
+    }
 }
 
+/// The outlets each cable connects, as `(plug, first_outlet, outlet)` links from the first
+/// plugged-in outlet to each of the others. Each plug is given as `(plug, cable, joined,
+/// outlet)`, like the fields of [`Plug`]. Cables joined end to end count as a single cable.
+fn cable_links(
+    plugs: &[(Entity, Entity, Option<Entity>, Option<Entity>)],
+) -> Vec<(Entity, Entity, Entity)> {
+    let cables: HashMap<Entity, Entity> = plugs
+        .iter()
+        .map(|&(plug, cable, _, _)| (plug, cable))
+        .collect();
+    let mut parents: HashMap<Entity, Entity> = HashMap::new();
+    fn root(parents: &HashMap<Entity, Entity>, mut cable: Entity) -> Entity {
+        while let Some(&parent) = parents.get(&cable) {
+            cable = parent;
+        }
+        cable
+    }
+    for &(_, cable, joined, _) in plugs {
+        let Some(&joined_cable) = joined.and_then(|joined| cables.get(&joined)) else {
+            continue;
+        };
+        let (a, b) = (root(&parents, cable), root(&parents, joined_cable));
+        if a != b {
+            parents.insert(a.max(b), a.min(b));
+        }
+    }
+
+    let mut plugged: HashMap<Entity, Vec<(Entity, Entity)>> = HashMap::new();
+    for &(plug, cable, _, outlet) in plugs {
+        if let Some(outlet) = outlet {
+            plugged
+                .entry(root(&parents, cable))
+                .or_default()
+                .push((plug, outlet));
+        }
+    }
+
+    let mut links = Vec::new();
+    for mut ends in plugged.into_values() {
+        ends.sort();
+        let (_, first_outlet) = ends[0];
+        links.extend(
+            ends[1..]
+                .iter()
+                .map(|&(plug, outlet)| (plug, first_outlet, outlet)),
+        );
+    }
+    links
+}
+
+/// Lights up wires while they are powered. Wires are spawned unlit, so they only light up once
+/// their net is.
 fn visible_wire_system(
     common: Res<Common>,
-    mut wire: Query<(&GlobalTransform, &mut MeshMaterial3d<StandardMaterial>), With<Wire>>,
-    grid: Res<PowerGrid>,
+    mut gained: EventReader<PowerGained>,
+    mut lost: EventReader<PowerLost>,
+    mut wire: Query<(&Channel, &mut MeshMaterial3d<StandardMaterial>), With<Wire>>,
 ) {
-    for (wire_transform, mut material) in wire.iter_mut() {
-        let wire_grid = global_to_grid(wire_transform.translation());
-        let is_powered = grid.active.contains(&wire_grid);
-        let expected_material = if is_powered {
-            &common.material_electricity
-        } else {
-            &common.material_dark_blue
-        };
-        if &material.0 != expected_material {
-            material.0 = expected_material.clone();
+    for event in gained.read() {
+        if let Ok((channel, mut material)) = wire.get_mut(event.entity) {
+            material.0 = common.electricity_material(*channel).clone();
+        }
+    }
+    for event in lost.read() {
+        if let Ok((_, mut material)) = wire.get_mut(event.entity) {
+            material.0 = common.material_dark_blue.clone();
         }
     }
 }
+
+fn visible_gate_system(
+    common: Res<Common>,
+    mut gained: EventReader<PowerGained>,
+    mut lost: EventReader<PowerLost>,
+    mut gates: Query<(&LogicGate, &mut MeshMaterial3d<StandardMaterial>)>,
+) {
+    for event in gained.read() {
+        if let Ok((gate, mut material)) = gates.get_mut(event.entity) {
+            material.0 = common.electricity_material(gate.output.channel).clone();
+        }
+    }
+    for event in lost.read() {
+        if let Ok((_, mut material)) = gates.get_mut(event.entity) {
+            material.0 = common.material_dark_blue.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entities<const N: usize>(first: u32) -> [Entity; N] {
+        std::array::from_fn(|i| Entity::from_raw(first + i as u32))
+    }
+
+    #[test]
+    fn splitters_link_their_first_outlet_to_the_others() {
+        let [a, b, c] = entities(0);
+        let [outlet_a, outlet_b, outlet_c] = entities(10);
+        let mut links = cable_links(&[
+            (a, a, None, Some(outlet_a)),
+            (b, a, None, Some(outlet_b)),
+            (c, a, None, Some(outlet_c)),
+        ]);
+        links.sort();
+        assert_eq!(
+            links,
+            vec![(b, outlet_a, outlet_b), (c, outlet_a, outlet_c)]
+        );
+    }
+
+    #[test]
+    fn joined_cables_act_as_one() {
+        let [a, b, c, d] = entities(0);
+        let [outlet_a, outlet_d] = entities(10);
+        let links = cable_links(&[
+            (a, a, None, Some(outlet_a)),
+            (b, a, Some(c), None),
+            (c, c, Some(b), None),
+            (d, c, None, Some(outlet_d)),
+        ]);
+        assert_eq!(links, vec![(d, outlet_a, outlet_d)]);
+    }
+
+    #[test]
+    fn joins_to_despawned_plugs_are_ignored() {
+        let [a, b, c, despawned] = entities(0);
+        let [outlet_a, outlet_c] = entities(10);
+        let links = cable_links(&[
+            (a, a, None, Some(outlet_a)),
+            (b, a, Some(despawned), None),
+            (c, c, None, Some(outlet_c)),
+        ]);
+        assert_eq!(links, vec![]);
+    }
+}
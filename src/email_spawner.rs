@@ -24,6 +24,15 @@ pub struct Particle {
     velocity: Vec3,
 }
 
+impl Particle {
+    pub fn new(time_left: f32, velocity: Vec3) -> Self {
+        Self {
+            time_left,
+            velocity,
+        }
+    }
+}
+
 pub fn spawn_emails(
     mut commands: Commands,
     time: Res<Time>,
@@ -1,10 +1,15 @@
 use bevy::prelude::*;
 
 use crate::{
-    common::Common, electricity::PowerGrid, player::Player, spawn_point::CurrentSpawnPoint,
+    common::Common,
+    electricity::{PowerGained, PowerLost, PowerPart, Powered},
+    player::Player,
+    power_network::PowerRole,
+    spawn_point::CurrentSpawnPoint,
 };
 
 #[derive(Component)]
+#[require(PowerPart = PowerPart::new(PowerRole::Reader))]
 pub struct EvilRobot {
     pub has_charge: bool,
 }
@@ -31,12 +36,19 @@ impl Plugin for EvilRobotPlugin {
 }
 
 fn set_robot_charge_system(
-    power_grid: Res<PowerGrid>,
-    mut robot: Query<(&GlobalTransform, &mut EvilRobot)>,
+    mut gained: EventReader<PowerGained>,
+    mut lost: EventReader<PowerLost>,
+    mut robots: Query<&mut EvilRobot>,
 ) {
-    for (robot_transform, mut robot) in robot.iter_mut() {
-        let grid_position = robot_transform.translation().xz().round().as_ivec2();
-        robot.has_charge = power_grid.active.contains(&grid_position);
+    for event in gained.read() {
+        if let Ok(mut robot) = robots.get_mut(event.entity) {
+            robot.has_charge = true;
+        }
+    }
+    for event in lost.read() {
+        if let Ok(mut robot) = robots.get_mut(event.entity) {
+            robot.has_charge = false;
+        }
     }
 }
 
@@ -78,13 +90,12 @@ pub struct Spinning(pub Vec3);
 
 pub fn spinning_system(
     time: Res<Time>,
-    mut spinning: Query<(&GlobalTransform, &mut Transform, &Spinning)>,
-    power: Res<PowerGrid>,
+    mut spinning: Query<(&ChildOf, &mut Transform, &Spinning)>,
+    powered: Query<(), With<Powered>>,
 ) {
     let delta = time.delta_secs();
-    for (global_transform, mut transform, spinning) in spinning.iter_mut() {
-        let grid = global_transform.translation().xz().round().as_ivec2();
-        let has_power = power.active.contains(&grid);
+    for (child_of, mut transform, spinning) in spinning.iter_mut() {
+        let has_power = powered.contains(child_of.parent());
 
         let target_scale = if has_power {
             Vec3::splat(4.)
@@ -1,6 +1,6 @@
 use bevy::{pbr::NotShadowCaster, platform::collections::HashSet, prelude::*};
 
-use crate::{common::Common, player::Player};
+use crate::{common::Common, level::Footprint, player::Player};
 
 pub struct FogPlugin;
 
@@ -41,12 +41,15 @@ fn clear_fog_system(
     time: Res<Time>,
     mut fog: Query<(&mut Transform, &mut Fog)>,
     player: Query<&GlobalTransform, With<Player>>,
-    clear: Query<(Entity, &GlobalTransform), (Without<DoesNotClearFog>, Without<Fog>)>,
+    clear: Query<
+        (Entity, &GlobalTransform, Option<&Footprint>),
+        (Without<DoesNotClearFog>, Without<Fog>),
+    >,
     parent: Query<&ChildOf>,
     does_not_clear_fog: Query<&DoesNotClearFog>,
 ) {
     let mut grid_to_clear: HashSet<IVec2> = HashSet::new();
-    for (entity, p) in clear.iter() {
+    for (entity, p, footprint) in clear.iter() {
         if parent
             .iter_ancestors(entity)
             .any(|ancestor| does_not_clear_fog.contains(ancestor))
@@ -54,6 +57,11 @@ fn clear_fog_system(
             continue;
         }
 
+        if let Some(footprint) = footprint {
+            grid_to_clear.extend(footprint.tiles(p.translation()));
+            continue;
+        }
+
         let p = p.translation().xz().round().as_ivec2();
         grid_to_clear.insert(p);
     }
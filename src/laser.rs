@@ -1,31 +1,154 @@
 use avian3d::prelude::*;
-use bevy::{pbr::light_consts::lux::RAW_SUNLIGHT, prelude::*};
+use bevy::{pbr::light_consts::lux::RAW_SUNLIGHT, platform::collections::HashSet, prelude::*};
+use serde::Deserialize;
 
-use crate::{common::Common, fog::DoesNotClearFog, level::LevelTag};
+use crate::{
+    common::Common,
+    electricity::{PowerPart, Powered, compute_charge_system},
+    fog::DoesNotClearFog,
+    level::LevelTag,
+    power_network::PowerRole,
+};
 
 pub struct LaserPlugin;
 
 #[derive(Component)]
 pub struct Laser {
     pub direction: Vec3,
-    pub beam: Option<Entity>,
+    /// The segments of the laser's beam, which is turned by mirrors and forked by splitters.
+    pub beams: Vec<Entity>,
+    /// The [`LaserEmitter`] that must be powered for the laser to fire. Lasers without one
+    /// always fire.
+    pub emitter: Option<Entity>,
+}
+
+/// The part of a powered laser source that takes charge from its net.
+#[derive(Component)]
+#[require(PowerPart = PowerPart::new(PowerRole::Load))]
+pub struct LaserEmitter;
+
+/// Powers its net while a laser beam hits it, and otherwise conducts like a wire.
+#[derive(Component, Default)]
+#[require(PowerPart = PowerPart::new(PowerRole::Conductor))]
+pub struct LaserReceptor {
+    pub lit: bool,
 }
 
 impl Plugin for LaserPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, draw_lasers_system);
+        app.add_systems(Update, (draw_lasers_system, visible_receptor_system))
+            .add_systems(
+                FixedUpdate,
+                (
+                    snap_mirrors_system,
+                    receptor_power_system.before(compute_charge_system),
+                ),
+            );
     }
 }
 
 #[derive(Component)]
 struct LaserBeam;
 
+/// How far a beam reaches before it fades out.
+const BEAM_RANGE: f32 = 150.;
+
+/// How many times a beam can be turned by mirrors before it stops.
+const MAX_BOUNCES: usize = 8;
+
+/// The most beam segments one laser can have, since every splitter forks the beam.
+const MAX_SEGMENTS: usize = 32;
+
+/// Which way a mirror runs across its tile, as seen in the level's map image.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum MirrorSlant {
+    /// From the bottom left to the top right, like `/`.
+    Slash,
+    /// From the top left to the bottom right, like `\`.
+    Backslash,
+}
+
+impl MirrorSlant {
+    /// The rotation that turns a mirror facing along X to run this way.
+    pub fn rotation(self) -> Quat {
+        match self {
+            Self::Slash => Quat::from_rotation_y(-std::f32::consts::FRAC_PI_4),
+            Self::Backslash => Quat::from_rotation_y(std::f32::consts::FRAC_PI_4),
+        }
+    }
+}
+
+/// Turns laser beams by 90 degrees. The mirror runs through the middle of the entity, facing
+/// along its local X axis.
+#[derive(Component)]
+pub struct Mirror {
+    /// Whether beams also pass straight through the mirror.
+    pub splits: bool,
+}
+
+/// The rotation about Y closest to `rotation` that leaves a mirror running diagonally across
+/// the grid.
+fn snap_to_diagonal(rotation: Quat) -> Quat {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+    let (yaw, _, _) = rotation.to_euler(EulerRot::YXZ);
+    let quarter_turns = ((yaw - FRAC_PI_4) / FRAC_PI_2).round();
+    Quat::from_rotation_y(FRAC_PI_4 + quarter_turns * FRAC_PI_2)
+}
+
+/// Keeps mirrors that can be pushed around, like mirror crates, from turning away from the
+/// diagonals, since beams would otherwise miss the grid after bouncing off them.
+fn snap_mirrors_system(mut mirrors: Query<(&mut Rotation, &mut AngularVelocity), With<Mirror>>) {
+    for (mut rotation, mut angular_velocity) in mirrors.iter_mut() {
+        let snapped = snap_to_diagonal(rotation.0);
+        if rotation.0.angle_between(snapped) > 0.001 {
+            rotation.0 = snapped;
+        }
+        if angular_velocity.y != 0.0 {
+            angular_velocity.y = 0.0;
+        }
+    }
+}
+
+/// One straight part of a laser's beam, still to be cast.
+struct BeamRay {
+    from: Vec3,
+    direction: Vec3,
+    bounces: usize,
+    /// The mirror the ray starts on, which it must not hit again.
+    mirror: Option<Entity>,
+}
+
+/// The axis-aligned direction closest to `v` on the ground plane.
+fn snap_to_axis(v: Vec3) -> Vec3 {
+    if v.x.abs() >= v.z.abs() {
+        Vec3::X * v.x.signum()
+    } else {
+        Vec3::Z * v.z.signum()
+    }
+}
+
+/// The direction a beam travelling along `direction` leaves a mirror with the given rotation.
+fn turn(direction: Vec3, mirror_rotation: Quat) -> Vec3 {
+    let normal = mirror_rotation * Vec3::X;
+    snap_to_axis(direction - 2.0 * direction.dot(normal) * normal)
+}
+
 fn draw_lasers_system(
     spatial: SpatialQuery,
     common: Res<Common>,
     mut commands: Commands,
     mut lasers: Query<(&Transform, &mut Laser, &LevelTag)>,
     mut beam: Query<&mut Transform, (With<LaserBeam>, Without<Laser>)>,
+    emitters: Query<Has<Powered>, With<LaserEmitter>>,
+    mut receptors: Query<(Entity, &mut LaserReceptor)>,
+    mirrors: Query<
+        (&Transform, &Mirror),
+        (
+            Without<LaserBeam>,
+            Without<Laser>,
+            Without<crate::player::Player>,
+        ),
+    >,
 
     mut player: Query<
         &mut Transform,
@@ -37,28 +160,85 @@ fn draw_lasers_system(
     >,
     current_spawn: Res<crate::spawn_point::CurrentSpawnPoint>,
 ) {
+    let mut lit = HashSet::new();
     for (laser_transform, mut laser, laser_level) in lasers.iter_mut() {
-        let cast = spatial.cast_ray(
-            laser_transform.translation + laser.direction * 0.05,
-            Dir3::try_from(laser.direction).unwrap_or(Dir3::X),
-            150.,
-            false,
-            &SpatialQueryFilter::default(),
-        );
+        let firing = laser
+            .emitter
+            .is_none_or(|emitter| emitters.get(emitter).unwrap_or(false));
+
+        let mut segments: Vec<(Vec3, Vec3)> = Vec::new();
+        let mut rays = Vec::new();
+        if firing {
+            rays.push(BeamRay {
+                from: laser_transform.translation,
+                direction: laser.direction,
+                bounces: 0,
+                mirror: None,
+            });
+        }
+        while let Some(ray) = rays.pop() {
+            if segments.len() >= MAX_SEGMENTS {
+                break;
+            }
+            let filter = SpatialQueryFilter::default().with_excluded_entities(ray.mirror);
+            let cast = spatial.cast_ray(
+                ray.from + ray.direction * 0.05,
+                Dir3::try_from(ray.direction).unwrap_or(Dir3::X),
+                BEAM_RANGE,
+                false,
+                &filter,
+            );
+            let Some(cast) = cast else {
+                segments.push((ray.from, ray.from + ray.direction * BEAM_RANGE));
+                continue;
+            };
 
-        if let Some(cast) = cast {
             if let Ok(mut player) = player.get_mut(cast.entity) {
                 // Respawn the player
                 player.translation = current_spawn.location;
             }
-            let to = laser_transform.translation + cast.distance * laser.direction;
-            let from = laser_transform.translation;
+            lit.insert(cast.entity);
+            let mut to = ray.from + (cast.distance + 0.05) * ray.direction;
+
+            if let Ok((mirror_transform, mirror)) = mirrors.get(cast.entity) {
+                let normal = mirror_transform.rotation * Vec3::X;
+                let facing = ray.direction.dot(normal);
+                if ray.bounces < MAX_BOUNCES && facing.abs() > 0.01 {
+                    // Turn where the beam crosses the middle of the mirror, not where it hits
+                    // the collider, so that the turned beam stays in line with the grid.
+                    to = ray.from
+                        + ray.direction
+                            * ((mirror_transform.translation - ray.from).dot(normal) / facing);
+                    rays.push(BeamRay {
+                        from: to,
+                        direction: turn(ray.direction, mirror_transform.rotation),
+                        bounces: ray.bounces + 1,
+                        mirror: Some(cast.entity),
+                    });
+                    if mirror.splits {
+                        rays.push(BeamRay {
+                            from: to,
+                            direction: ray.direction,
+                            bounces: ray.bounces + 1,
+                            mirror: Some(cast.entity),
+                        });
+                    }
+                }
+            }
+            segments.push((ray.from, to));
+        }
 
+        // Reuse the laser's beam segments, and add or remove some to match the traced beam.
+        let kept = segments.len().min(laser.beams.len());
+        for extra in laser.beams.drain(kept..) {
+            commands.entity(extra).despawn();
+        }
+        for (i, &(from, to)) in segments.iter().enumerate() {
             let beam_transform = Transform::from_translation((to + from) / 2.)
                 .looking_at(to, Vec3::Y)
                 .with_scale(Vec3::new(0.1, 0.1, to.distance(from) + 0.1));
 
-            if let Some(beam_id) = laser.beam {
+            if let Some(&beam_id) = laser.beams.get(i) {
                 if let Ok(mut beam) = beam.get_mut(beam_id) {
                     *beam = beam_transform;
                 };
@@ -93,8 +273,77 @@ fn draw_lasers_system(
                     ))
                     .id();
 
-                laser.beam = Some(beam_id);
+                laser.beams.push(beam_id);
             }
         }
     }
+
+    for (entity, mut receptor) in receptors.iter_mut() {
+        let hit = lit.contains(&entity);
+        if receptor.lit != hit {
+            receptor.lit = hit;
+        }
+    }
+}
+
+fn receptor_power_system(mut receptors: Query<(&LaserReceptor, &mut PowerPart)>) {
+    for (receptor, mut part) in receptors.iter_mut() {
+        let role = if receptor.lit {
+            PowerRole::Source
+        } else {
+            PowerRole::Conductor
+        };
+        if part.role != role {
+            part.role = role;
+        }
+    }
+}
+
+fn visible_receptor_system(
+    common: Res<Common>,
+    mut receptors: Query<(&LaserReceptor, &mut MeshMaterial3d<StandardMaterial>)>,
+) {
+    for (receptor, mut material) in receptors.iter_mut() {
+        let expected_material = if receptor.lit {
+            &common.material_electricity
+        } else {
+            &common.material_dark_gray
+        };
+        if &material.0 != expected_material {
+            material.0 = expected_material.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mirrors_turn_beams_the_way_they_slant() {
+        // The map's rows run along +Z, so "up" in the image is -Z.
+        let slash = MirrorSlant::Slash.rotation();
+        assert_eq!(turn(Vec3::X, slash), Vec3::NEG_Z);
+        assert_eq!(turn(Vec3::NEG_X, slash), Vec3::Z);
+        assert_eq!(turn(Vec3::Z, slash), Vec3::NEG_X);
+        assert_eq!(turn(Vec3::NEG_Z, slash), Vec3::X);
+
+        let backslash = MirrorSlant::Backslash.rotation();
+        assert_eq!(turn(Vec3::X, backslash), Vec3::Z);
+        assert_eq!(turn(Vec3::NEG_X, backslash), Vec3::NEG_Z);
+        assert_eq!(turn(Vec3::Z, backslash), Vec3::X);
+        assert_eq!(turn(Vec3::NEG_Z, backslash), Vec3::NEG_X);
+    }
+
+    #[test]
+    fn pushed_mirrors_snap_back_to_a_diagonal() {
+        let nudged = MirrorSlant::Slash.rotation() * Quat::from_rotation_y(0.3);
+        assert!(snap_to_diagonal(nudged).angle_between(MirrorSlant::Slash.rotation()) < 0.001);
+        let turned = Quat::from_rotation_y(std::f32::consts::PI - 0.9);
+        assert!(
+            snap_to_diagonal(turned)
+                .angle_between(Quat::from_rotation_y(3.0 * std::f32::consts::FRAC_PI_4))
+                < 0.001
+        );
+    }
 }
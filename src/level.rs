@@ -1,32 +1,46 @@
-use std::collections::VecDeque;
 use std::sync::Mutex;
 
 use avian3d::prelude::*;
 use bevy::{
-    color::color_difference::EuclideanDistance, image::ImageLoaderSettings,
-    platform::collections::HashMap, platform::collections::HashSet, prelude::*,
+    asset::LoadState, image::ImageLoaderSettings, platform::collections::HashMap,
+    platform::collections::HashSet, prelude::*,
 };
 
 use crate::{
-    chain::ChainLink,
+    active_levels::{
+        ActiveLevels, LevelLoaded, LevelState, LevelStateChanged, LevelUnloaded,
+        update_level_states_system,
+    },
+    capacitor::{Battery, Capacitor, ChargeLevel, DEFAULT_CAPACITY},
+    chain::{ChainLink, ChainSnap, LinkLength},
     common::Common,
     door::Door,
     draggable::Draggable,
-    electricity::{Outlet, Plug, PowerSource, Wire},
+    electricity::{
+        Channel, DEFAULT_REEL_LENGTH, Diode, DiodeDirection, GatePin, GridLayer, LogicGate, Outlet,
+        Plug, PowerPart, PowerSource, Wire,
+    },
     evil_robot::{EvilRobot, Spinning},
     fog::DoesNotClearFog,
     interactible::Interactible,
-    laser::Laser,
+    laser::{Laser, LaserEmitter, LaserReceptor, Mirror, MirrorSlant},
+    level_manifest::{LevelManifest, SignAppear, TileSettings},
+    level_spawning::{LevelCommands, LevelSpawnBudget, SpawnQueue, spawn_queued_system},
     mainframe::{Mainframe, WinMainframe},
+    palette::{Palette, Tile},
+    parsed_level::{ChainGroup, HallwayJunction, HallwayPattern, ParsedLevel, pair_hallways},
     player::Player,
-    ruby::{MakeRuby, Ruby},
+    power_network::{GateKind, PowerRole},
+    pressure_plate::{PlateMode, PressurePlate},
+    ron_asset::RonAssetLoader,
+    ruby::{MakeRuby, Ruby, ShowWhenRubyCollected},
     spawn_point::SpawnPoint,
+    switch::{DEFAULT_BUTTON_DURATION, Switch, SwitchKind, SwitchTimerBar},
     well::{DespawnFalling, Well},
+    wireless::{Receiver, Transmitter},
     zipline::Zipline,
 };
 
-const STARTING_LEVEL: &str = "level_1.png";
-
 pub struct LevelPlugin;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
@@ -35,7 +49,7 @@ pub struct LevelName {
 }
 
 impl LevelName {
-    fn from_string(level_name: String) -> Self {
+    pub(crate) fn from_string(level_name: String) -> Self {
         Self { level_name }
     }
 }
@@ -48,7 +62,61 @@ impl std::fmt::Display for LevelName {
 
 #[derive(Resource)]
 pub struct Levels {
-    levels: HashMap<LevelName, Handle<Image>>,
+    manifest: Handle<LevelManifest>,
+    palette: Handle<Palette>,
+    starting_level: Option<LevelName>,
+    levels: HashMap<LevelName, LevelInfo>,
+}
+
+impl Levels {
+    /// Returns the metadata for the level, once the manifest has loaded.
+    pub fn info(&self, level: &LevelName) -> Option<&LevelInfo> {
+        self.levels.get(level)
+    }
+}
+
+/// Everything the manifest says about a single level.
+pub struct LevelInfo {
+    pub image: Handle<Image>,
+    pub display_name: String,
+    pub music: Option<String>,
+    pub signs: Vec<Sign>,
+    /// Per-tile settings from the manifest, by grid position.
+    pub tile_settings: HashMap<IVec2, TileSettings>,
+}
+
+pub struct Sign {
+    pub material: Handle<StandardMaterial>,
+    pub position: Vec3,
+    pub facing: Vec3,
+    pub up: Vec3,
+    pub scale: f32,
+    pub appear: SignAppear,
+}
+
+/// The tiles covered by an entity that stands in for a rectangle of merged tiles, centered on its
+/// transform.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct Footprint {
+    pub size: IVec2,
+}
+
+impl Footprint {
+    /// The grid positions of each of the covered tiles.
+    pub fn tiles(&self, center: Vec3) -> impl Iterator<Item = IVec2> {
+        let corner = (center.xz() - (self.size - IVec2::ONE).as_vec2() / 2.)
+            .round()
+            .as_ivec2();
+        let size = self.size;
+        (0..size.x).flat_map(move |dx| (0..size.y).map(move |dy| corner + IVec2::new(dx, dy)))
+    }
+
+    /// The distance from the point to the center of the closest covered tile.
+    pub fn distance(&self, center: Vec3, point: Vec3) -> f32 {
+        let half = (self.size - IVec2::ONE).as_vec2() / 2.;
+        let closest = center.xz() + (point.xz() - center.xz()).clamp(-half, half);
+        Vec3::new(closest.x, center.y, closest.y).distance(point)
+    }
 }
 
 #[derive(Component, Clone, Eq, PartialEq, Debug, Hash)]
@@ -64,41 +132,111 @@ impl LevelTag {
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_levels_system)
-            .add_systems(Update, load_level_system);
+        app.init_asset::<LevelManifest>()
+            .init_asset::<Palette>()
+            .register_asset_loader(RonAssetLoader::<LevelManifest>::new(&["levels.ron"]))
+            .register_asset_loader(RonAssetLoader::<Palette>::new(&["palette.ron"]))
+            .init_resource::<ActiveLevels>()
+            .init_resource::<ParsedLevels>()
+            .init_resource::<SpawnQueue>()
+            .init_resource::<LevelSpawnBudget>()
+            .add_event::<LevelStateChanged>()
+            .add_event::<LevelLoaded>()
+            .add_event::<LevelUnloaded>()
+            .add_systems(Startup, setup_levels_system)
+            .add_systems(
+                Update,
+                (
+                    read_level_manifest_system,
+                    load_level_system,
+                    spawn_queued_system,
+                    update_level_states_system,
+                    remove_hallway_blockers_system,
+                )
+                    .chain(),
+            );
     }
 }
 
 fn setup_levels_system(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let map_names = [
-        "level_1.png",
-        "level_2.png",
-        "level_3.png",
-        "level_4.png",
-        "level_5.png",
-        "level_6.png",
-        "level_7.png",
-        "level_8.png",
-        "rails_map.png",
-        "level_big.png",
-    ];
-
     commands.insert_resource(Levels {
-        levels: map_names
+        manifest: asset_server.load("manifest.levels.ron"),
+        palette: asset_server.load("tiles.palette.ron"),
+        starting_level: None,
+        levels: HashMap::new(),
+    });
+}
+
+/// Once the manifest has loaded, starts loading each of the levels it lists.
+fn read_level_manifest_system(
+    mut levels: ResMut<Levels>,
+    asset_server: Res<AssetServer>,
+    manifests: Res<Assets<LevelManifest>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if levels.starting_level.is_some() {
+        return;
+    }
+
+    if let LoadState::Failed(err) = asset_server.load_state(&levels.manifest) {
+        panic!("failed to load the level manifest: {err}");
+    }
+    let Some(manifest) = manifests.get(&levels.manifest) else {
+        // The manifest has not yet loaded.
+        return;
+    };
+    if let Err(err) = manifest.validate() {
+        panic!("invalid level manifest: {err}");
+    }
+
+    let mut sign_materials: HashMap<&str, Handle<StandardMaterial>> = HashMap::new();
+    let mut level_infos: HashMap<LevelName, LevelInfo> = HashMap::new();
+    for entry in manifest.levels.iter() {
+        let signs = entry
+            .signs
             .iter()
-            .map(|map_name| {
-                (
-                    LevelName::from_string(map_name.to_string()),
-                    asset_server.load_with_settings(
-                        *map_name,
-                        |settings: &mut ImageLoaderSettings| {
-                            settings.is_srgb = false; // It's linear
-                        },
-                    ),
-                )
+            .map(|sign| Sign {
+                material: sign_materials
+                    .entry(&sign.image)
+                    .or_insert_with(|| {
+                        materials.add(StandardMaterial {
+                            base_color_texture: Some(asset_server.load(&sign.image)),
+                            perceptual_roughness: 1.0,
+                            ..default()
+                        })
+                    })
+                    .clone(),
+                position: Vec3::from(sign.position),
+                facing: Vec3::from(sign.facing),
+                up: Vec3::from(sign.up),
+                scale: sign.scale,
+                appear: sign.appear,
             })
-            .collect(),
-    });
+            .collect();
+
+        level_infos.insert(
+            LevelName::from_string(entry.file.clone()),
+            LevelInfo {
+                image: asset_server.load_with_settings(
+                    &entry.file,
+                    |settings: &mut ImageLoaderSettings| {
+                        settings.is_srgb = false; // It's linear
+                    },
+                ),
+                display_name: entry.display_name.clone().unwrap_or(entry.file.clone()),
+                music: entry.music.clone(),
+                signs,
+                tile_settings: entry
+                    .tile_settings
+                    .iter()
+                    .map(|settings| (IVec2::from(settings.position), settings.clone()))
+                    .collect(),
+            },
+        );
+    }
+
+    levels.starting_level = Some(LevelName::from_string(manifest.starting_level.clone()));
+    levels.levels = level_infos;
 }
 
 #[allow(unused)]
@@ -113,9 +251,82 @@ struct Hallway {
     rooms: Vec<LevelName>,
 }
 
-struct Resetting {
-    time_left: f32,
-    locus: Vec3,
+/// Keeps the player from walking out of a hallway before the level on the other side has spawned.
+#[derive(Component)]
+struct HallwayBlocker {
+    level: LevelName,
+}
+
+/// How long a level lingers after the player leaves it, in case they come back.
+const RESET_DELAY: f32 = 4.0;
+
+/// The parsed map of every level, and which levels each hallway connects.
+#[derive(Resource, Default)]
+pub struct ParsedLevels {
+    levels: HashMap<LevelName, ParsedLevel>,
+    junction_to_levels: HashMap<HallwayPattern, Vec<LevelName>>,
+}
+
+impl ParsedLevels {
+    pub fn get(&self, level: &LevelName) -> Option<&ParsedLevel> {
+        self.levels.get(level)
+    }
+
+    /// The hallways of the level, along with the level on the other side of each.
+    pub fn neighbors<'a>(
+        &'a self,
+        level: &'a LevelName,
+    ) -> impl Iterator<Item = (&'a HallwayJunction, &'a LevelName)> {
+        self.levels[level]
+            .hallways
+            .iter()
+            .filter_map(move |junction| {
+                self.junction_to_levels[&junction.pattern]
+                    .iter()
+                    .find(|other| *other != level)
+                    .map(|other| (junction, other))
+            })
+    }
+
+    /// Where `to` must be placed so that its end of the hallway lines up with the end in `from`.
+    pub fn hallway_shift(
+        &self,
+        from: &LevelName,
+        from_shift: Vec3,
+        to: &LevelName,
+        pattern: &HallwayPattern,
+    ) -> Vec3 {
+        // Hallways are paired up when the levels are parsed, so both sides exist.
+        let old_hallway = self.levels[from]
+            .hallways
+            .iter()
+            .find(|h| h.pattern == *pattern)
+            .expect("hallway must exist in the level it was spawned from");
+        let new_hallway = self.levels[to]
+            .hallways
+            .iter()
+            .find(|h| h.pattern == *pattern)
+            .expect("hallway must exist in the level it connects to");
+
+        from_shift + Vec3::new(old_hallway.center.x, 0.0, old_hallway.center.y)
+            - Vec3::new(new_hallway.center.x, 0.0, new_hallway.center.y)
+    }
+
+    /// Whether the level would overlap any other spawned level, besides those in `ignore`.
+    fn overlaps_any(
+        &self,
+        level: &LevelName,
+        shift: Vec3,
+        active_levels: &ActiveLevels,
+        ignore: &[LevelName],
+    ) -> bool {
+        active_levels.iter().any(|(other, active)| {
+            active.state.is_spawned()
+                && other != level
+                && !ignore.contains(other)
+                && self.levels[level].overlaps(shift, &self.levels[other], active.shift)
+        })
+    }
 }
 
 fn load_level_system(
@@ -123,76 +334,200 @@ fn load_level_system(
     mut commands: Commands,
     levels: Res<Levels>,
     image_assets: Res<Assets<Image>>,
-    mut active_levels: Local<HashMap<LevelName, Vec3>>,
-    // These levels are being reset, but may be restored if needed.
-    mut resetting_levels: Local<HashMap<LevelName, Resetting>>,
+    palettes: Res<Assets<Palette>>,
+    asset_server: Res<AssetServer>,
+    mut active_levels: ResMut<ActiveLevels>,
+    mut spawn_queue: ResMut<SpawnQueue>,
     mut has_loaded_player: Local<bool>,
 
     common: Res<Common>,
 
-    level_items: Query<(Entity, &Transform, &LevelTag)>,
+    level_items: Query<(Entity, &Transform, &LevelTag, Option<&Footprint>)>,
     player: Query<&Transform, With<Player>>,
     hallways: Query<(&Transform, &Hallway, &LevelTag)>,
 
-    mut hallway_junctions: Local<HashMap<LevelName, Vec<HallwayJunction>>>,
-    mut junction_to_levels: Local<HashMap<HallwayPattern, Vec<LevelName>>>,
+    mut parsed_levels: ResMut<ParsedLevels>,
+
+    #[cfg(feature = "dev")] mut image_events: EventReader<AssetEvent<Image>>,
 ) {
+    let Some(starting_level) = levels.starting_level.clone() else {
+        // The manifest has not yet loaded.
+        return;
+    };
+
     // Check that all levels are loaded
-    for level in levels.levels.values() {
-        if !image_assets.contains(level) {
+    for (level, info) in levels.levels.iter() {
+        if let LoadState::Failed(err) = asset_server.load_state(&info.image) {
+            panic!("failed to load level {level}: {err}");
+        }
+        if !image_assets.contains(&info.image) {
             // This level has not yet loaded.
             return;
         }
     }
 
-    if hallway_junctions.is_empty() {
-        *hallway_junctions = levels
+    if let LoadState::Failed(err) = asset_server.load_state(&levels.palette) {
+        panic!("failed to load the tile palette: {err}");
+    }
+    let Some(palette) = palettes.get(&levels.palette) else {
+        // The palette has not yet loaded.
+        return;
+    };
+
+    if parsed_levels.levels.is_empty() {
+        if let Err(err) = palette.validate() {
+            panic!("invalid tile palette: {err}");
+        }
+
+        parsed_levels.levels = levels
             .levels
             .iter()
-            .map(|(level, image)| {
+            .map(|(level, info)| {
                 (
                     level.clone(),
-                    get_hallway_junctions(image_assets.get(image).unwrap()),
+                    ParsedLevel::from_image(image_assets.get(&info.image).unwrap(), palette),
                 )
             })
             .collect();
+        parsed_levels.junction_to_levels = get_junction_levels(&parsed_levels.levels);
+    }
 
-        for (level, junctions) in hallway_junctions.iter() {
-            for junction in junctions.iter() {
-                junction_to_levels
-                    .entry(junction.pattern)
-                    .or_default()
-                    .push(level.clone());
+    let despawn_level = |commands: &mut Commands, level: &LevelName| {
+        for (entity, _, entity_level, _) in level_items.iter() {
+            if entity_level.level == *level {
+                commands.entity(entity).despawn();
             }
         }
+    };
+    let reset_level =
+        |commands: &mut Commands, active_levels: &mut ActiveLevels, level: &LevelName| {
+            for (entity, _, entity_level, _) in level_items.iter() {
+                if entity_level.level == *level {
+                    commands.entity(entity).insert(DoesNotClearFog);
+                }
+            }
+            let shift = active_levels.get(level).unwrap().shift;
+            active_levels.set_state(
+                level,
+                LevelState::Resetting {
+                    time_left: RESET_DELAY,
+                },
+                shift,
+            );
+        };
+
+    // When a level's image changes, respawn it in place.
+    #[cfg(feature = "dev")]
+    for event in image_events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        let Some((level, info)) = levels
+            .levels
+            .iter()
+            .find(|(_, info)| info.image.id() == *id)
+        else {
+            continue;
+        };
+        let Some(image) = image_assets.get(*id) else {
+            continue;
+        };
+
+        info!("reloading level {level}");
+        let old_parsed = parsed_levels
+            .levels
+            .insert(level.clone(), ParsedLevel::from_image(image, palette));
+        match pair_hallways(parsed_levels.levels.iter()) {
+            Ok(junction_to_levels) => parsed_levels.junction_to_levels = junction_to_levels,
+            Err(errors) => {
+                // Keep playing with the old version of the level until the edit is fixed.
+                for err in errors.iter() {
+                    error!("{err}");
+                }
+                error!(
+                    "not reloading level {level}, since {} hallways could not be paired",
+                    errors.len()
+                );
+                if let Some(old_parsed) = old_parsed {
+                    parsed_levels.levels.insert(level.clone(), old_parsed);
+                }
+                continue;
+            }
+        }
+
+        // Levels that aren't spawned, or are about to despawn, pick up the change when they are
+        // next spawned.
+        if !matches!(
+            active_levels.state(level),
+            LevelState::Loading | LevelState::Active
+        ) {
+            continue;
+        }
+        let Some(shift) = active_levels.shift(level) else {
+            continue;
+        };
+        spawn_queue.cancel(level);
+        despawn_level(&mut commands, level);
+        active_levels.set_state(level, LevelState::Despawned, shift);
+        load_level(
+            shift,
+            LevelTag {
+                level: level.clone(),
+            },
+            &mut spawn_queue.for_level(level),
+            &common,
+            info,
+            &parsed_levels.levels[level],
+            false,
+            &parsed_levels.junction_to_levels,
+        );
+        active_levels.set_state(level, LevelState::Loading, shift);
     }
 
     // If the player is in a hallway, load both levels.
     // If there is no player, load the first level.
 
     if !*has_loaded_player {
-        let first_level = LevelName::from_string(STARTING_LEVEL.to_string());
+        let first_level_info = &levels.levels[&starting_level];
         load_level(
             Vec3::ZERO,
             LevelTag {
-                level: first_level.clone(),
+                level: starting_level.clone(),
             },
-            &mut commands,
+            &mut spawn_queue.for_level(&starting_level),
             &common,
-            image_assets.get(&levels.levels[&first_level]).unwrap(),
+            first_level_info,
+            &parsed_levels.levels[&starting_level],
             true,
-            &junction_to_levels,
+            &parsed_levels.junction_to_levels,
         );
         *has_loaded_player = true;
-        active_levels.insert(first_level, Vec3::ZERO);
+        active_levels.set_state(&starting_level, LevelState::Loading, Vec3::ZERO);
         return;
     }
 
-    // Figure out which room the player is in.
     let Ok(player) = player.single() else {
         return;
     };
 
+    let distance_to_player = |t: &Transform, footprint: Option<&Footprint>| match footprint {
+        Some(footprint) => footprint.distance(t.translation, player.translation),
+        None => t.translation.distance(player.translation),
+    };
+
+    // Figure out which room the player is in.
+    let closest_level = level_items
+        .iter()
+        .min_by_key(|(_, t, _level, footprint)| (distance_to_player(t, *footprint) * 100.) as i64)
+        .map(|(_, _, level, _)| level.level.clone());
+    if let Some(closest_level) = closest_level.as_ref()
+        && active_levels.current() != Some(closest_level)
+    {
+        active_levels.set_current(closest_level.clone());
+    }
+
+    let parsed_levels = &*parsed_levels;
+
     // If the player is in a hallway, this is a special case.
 
     let mut is_in_hall = false;
@@ -201,393 +536,289 @@ fn load_level_system(
             is_in_hall = true;
             // Player is in the hallway!
             for level_to_load in hallway.rooms.iter() {
-                if resetting_levels.contains_key(level_to_load) {
+                if let LevelState::Resetting { .. } = active_levels.state(level_to_load) {
                     // Restore the level instead of allowing it to despawn.
-                    active_levels.insert(
-                        level_to_load.clone(),
-                        resetting_levels.remove(level_to_load).unwrap().locus,
-                    );
-                    for (item_entity, _, level) in level_items.iter() {
+                    let shift = active_levels.shift(level_to_load).unwrap();
+                    let state = if spawn_queue.is_loading(level_to_load) {
+                        LevelState::Loading
+                    } else {
+                        LevelState::Active
+                    };
+                    active_levels.set_state(level_to_load, state, shift);
+                    for (item_entity, _, level, _) in level_items.iter() {
                         if level.level == *level_to_load {
                             commands.entity(item_entity).remove::<DoesNotClearFog>();
                         }
                     }
                 }
 
-                if !active_levels.contains_key(level_to_load) {
-                    let old_level_junctions = &hallway_junctions[&hallway_level.level];
-                    let new_level_image = image_assets.get(&levels.levels[level_to_load]).unwrap();
-                    let new_level_junctions = &hallway_junctions[level_to_load];
-
-                    let Some(old_hallway) = old_level_junctions
-                        .iter()
-                        .find(|h| h.pattern == hallway.pattern)
-                    else {
-                        eprintln!(
-                            "failed to load new level {} from level {} - the old hallway pattern not found",
-                            level_to_load, hallway_level.level,
-                        );
-                        continue;
-                    };
+                if !active_levels.is_active(level_to_load) {
+                    let old_shift = active_levels.get(&hallway_level.level).unwrap().shift;
+                    let new_shift = parsed_levels.hallway_shift(
+                        &hallway_level.level,
+                        old_shift,
+                        level_to_load,
+                        &hallway.pattern,
+                    );
 
-                    let Some(new_hallway) = new_level_junctions
+                    // Make room for the new level by resetting anything in its way.
+                    let in_the_way: Vec<LevelName> = active_levels
                         .iter()
-                        .find(|h| h.pattern == hallway.pattern)
-                    else {
-                        eprintln!(
-                            "failed to load new level {} from level {} - the new hallway pattern not found",
-                            level_to_load, hallway_level.level,
-                        );
-                        continue;
-                    };
+                        .filter(|(other, active)| {
+                            active_levels.is_active(other)
+                                && !hallway.rooms.contains(other)
+                                && parsed_levels.levels[*other].overlaps(
+                                    active.shift,
+                                    &parsed_levels.levels[level_to_load],
+                                    new_shift,
+                                )
+                        })
+                        .map(|(other, _)| other.clone())
+                        .collect();
+                    for other in in_the_way.iter() {
+                        reset_level(&mut commands, &mut active_levels, other);
+                    }
 
-                    let old_shift = active_levels[&hallway_level.level];
-                    // let old_hall_at = old_shift + Vec3::new(old_hallway.center.x, 0.0, old_hallway.center.y)
-                    // let new_hall_at = new_shift + Vec3::new(new_hallway.center.x, 0.0, new_hallway.center.y)
-                    let new_shift = old_shift
-                        + Vec3::new(old_hallway.center.x, 0.0, old_hallway.center.y)
-                        - Vec3::new(new_hallway.center.x, 0.0, new_hallway.center.y);
-
-                    load_level(
-                        new_shift,
-                        LevelTag {
-                            level: level_to_load.clone(),
-                        },
+                    start_loading_neighbor(
                         &mut commands,
+                        &mut spawn_queue,
+                        &mut active_levels,
                         &common,
-                        new_level_image,
-                        false,
-                        &junction_to_levels,
+                        &levels,
+                        parsed_levels,
+                        &hallway_level.level,
+                        level_to_load,
+                        &hallway.pattern,
                     );
-                    active_levels.insert(level_to_load.clone(), new_shift);
                 }
             }
         }
     }
 
-    if !is_in_hall && active_levels.len() >= 2 {
+    let current_active_levels: Vec<LevelName> = active_levels
+        .iter()
+        .filter(|(level, _)| active_levels.is_active(level))
+        .map(|(level, _)| level.clone())
+        .collect();
+    if !is_in_hall && current_active_levels.len() >= 2 {
         // Despawn all of the other levels.
-        let closest_level = level_items.iter().min_by_key(|(_, t, _level)| {
-            (t.translation.distance(player.translation) * 100.) as i64
-        });
-
-        if let Some((_, _, closest_level)) = closest_level {
+        if let Some(closest_level) = closest_level.as_ref() {
             let mut must_keep: HashSet<&LevelName> = HashSet::new();
-            for (_, t, level) in level_items.iter() {
-                if t.translation.distance(player.translation) < 2.5 {
+            for (_, t, level, footprint) in level_items.iter() {
+                if distance_to_player(t, footprint) < 2.5 {
                     must_keep.insert(&level.level);
                 }
             }
 
-            let current_active_levels = std::mem::take(&mut *active_levels);
-            for (current_key, current_state) in current_active_levels {
-                if current_key == closest_level.level || must_keep.contains(&current_key) {
-                    // Retain this level.
-                    active_levels.insert(current_key, current_state);
-                } else {
-                    // This level must be marked for deletion.
-                    for (entity, _, level) in level_items.iter() {
-                        if level.level == current_key {
-                            commands.entity(entity).insert(DoesNotClearFog);
-                        }
-                    }
-                    resetting_levels.insert(
-                        current_key,
-                        Resetting {
-                            time_left: 4.0,
-                            locus: current_state,
-                        },
-                    );
+            // Preloaded neighbors are kept too, as long as they still line up with this level.
+            let closest_shift = active_levels.get(closest_level).unwrap().shift;
+            for (junction, neighbor) in parsed_levels.neighbors(closest_level) {
+                let expected_shift = parsed_levels.hallway_shift(
+                    closest_level,
+                    closest_shift,
+                    neighbor,
+                    &junction.pattern,
+                );
+                if active_levels
+                    .shift(neighbor)
+                    .is_some_and(|shift| shift.distance(expected_shift) < 0.01)
+                {
+                    must_keep.insert(neighbor);
                 }
             }
-        }
-    }
 
-    resetting_levels.retain(|level, resetting| {
-        let will_despawn = resetting.time_left < 0.0;
-        resetting.time_left -= time.delta_secs();
-        if will_despawn {
-            for (entity, _, entity_level) in level_items.iter() {
-                if entity_level.level == *level {
-                    commands.entity(entity).despawn();
+            for current_key in current_active_levels.iter() {
+                if current_key == closest_level || must_keep.contains(current_key) {
+                    // Retain this level.
+                    continue;
                 }
+
+                // This level must be marked for deletion.
+                reset_level(&mut commands, &mut active_levels, current_key);
             }
-            // Remove the level from this list
-            false
-        } else {
-            // Keep the level alive for now
-            true
         }
-    });
-}
-
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
-struct HallwayPattern(u32);
-
-#[derive(Debug)]
-struct HallwayJunction {
-    pattern: HallwayPattern,
-    center: Vec2,
-    grids: Vec<IVec2>,
-}
-
-fn get_hallway_junctions(image: &Image) -> Vec<HallwayJunction> {
-    fn is_hallway_color(c: &Color) -> bool {
-        c.distance(&Color::linear_rgb(0.5, 0.5, 0.5)) < 0.1
-            || c.distance(&Color::linear_rgb(0.625, 0.625, 0.625)) < 0.1
-            || c.distance(&Color::linear_rgb(0.75, 0.75, 0.75)) < 0.1
     }
-    let mut visited: HashSet<IVec2> = HashSet::new();
-    let mut patterns: Vec<HallwayJunction> = Vec::new();
 
-    for x in 0..image.width() as i32 {
-        for y in 0..image.height() as i32 {
-            let p = IVec2::new(x, y);
-
-            if visited.contains(&p) {
+    // Once the player's level has fully spawned, preload its neighbors in the background.
+    if let Some(closest_level) = closest_level.as_ref()
+        && !is_in_hall
+        && spawn_queue.is_empty()
+        && active_levels.state(closest_level) == LevelState::Active
+    {
+        let closest_shift = active_levels.get(closest_level).unwrap().shift;
+        for (junction, neighbor) in parsed_levels.neighbors(closest_level) {
+            if active_levels.state(neighbor).is_spawned() {
                 continue;
             }
-
-            let color = image.get_color_at(x as u32, y as u32).unwrap();
-            if !is_hallway_color(&color) {
+            let shift = parsed_levels.hallway_shift(
+                closest_level,
+                closest_shift,
+                neighbor,
+                &junction.pattern,
+            );
+            if parsed_levels.overlaps_any(
+                neighbor,
+                shift,
+                &active_levels,
+                std::slice::from_ref(closest_level),
+            ) {
+                // It can't be spawned until the player is in the hallway.
                 continue;
             }
 
-            visited.insert(p);
-            let mut region: HashSet<IVec2> = HashSet::new();
-            region.insert(p);
-            let mut stack = vec![p];
-            while let Some(curr) = stack.pop() {
-                for d in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
-                    let neighbor = curr + d;
-                    if visited.contains(&neighbor) {
-                        continue;
-                    }
-                    let neighbor_color = image
-                        .get_color_at(neighbor.x as u32, neighbor.y as u32)
-                        .unwrap();
-                    if !is_hallway_color(&neighbor_color) {
-                        continue;
-                    }
-
-                    visited.insert(neighbor);
-                    region.insert(neighbor);
-                    stack.push(neighbor);
-                }
-            }
-
-            let mut region_pattern = region.iter().copied().collect::<Vec<IVec2>>();
-            region_pattern.sort_by_key(|p| (p.x, p.y));
-            let region_pattern: HallwayPattern = HallwayPattern(
-                region_pattern
-                    .iter()
-                    .map(|p| {
-                        let color = image
-                            .get_color_at(p.x as u32, p.y as u32)
-                            .unwrap()
-                            .to_linear()
-                            .red;
-                        if color < 0.56 {
-                            1
-                        } else if color < 0.688 {
-                            2
-                        } else {
-                            3
-                        }
-                    })
-                    .fold(0, |a, b| a * 4 + b),
+            start_loading_neighbor(
+                &mut commands,
+                &mut spawn_queue,
+                &mut active_levels,
+                &common,
+                &levels,
+                parsed_levels,
+                closest_level,
+                neighbor,
+                &junction.pattern,
             );
-
-            patterns.push(HallwayJunction {
-                pattern: region_pattern,
-                center: region.iter().map(|p| p.as_vec2()).sum::<Vec2>() / region.len() as f32,
-                grids: region.iter().copied().collect(),
-            })
         }
     }
 
-    patterns
-}
-
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
-enum Tile {
-    Floor,
-    ElevatedFloor,
-    Ramp,
-    Hallway(i32),
-    Wall,
-    ComputerMainframe,
-    ComputerMainframeBig,
-    Outside,
-    Zappy,
-    Well,
-    Door,
-    Crate,
-    LaserSource,
-    PlayerStart,
-    SpawnPoint,
-    Zipline,
-    ZiplineOverWell,
-    Chain,
-    Outlet,
-    PowerSource,
-    FloorWire,
-    CrossFloorWire,
-    WallWire,
-    Ruby,
+    for level in active_levels.tick_resetting(time.delta_secs()) {
+        spawn_queue.cancel(&level);
+        despawn_level(&mut commands, &level);
+        let shift = active_levels.get(&level).unwrap().shift;
+        active_levels.set_state(&level, LevelState::Despawned, shift);
+    }
 }
 
-/// Converts from the color of the pixel to the type of tile.
-fn color_to_tile(color: &Color) -> Option<Tile> {
-    #[allow(clippy::eq_op)]
-    static MAPPING: [(Color, Tile); 26] = [
-        // White == Floor
-        (Color::linear_rgb(1., 1., 1.), Tile::Floor),
-        // Light Blue == Elevated Floor
-        (Color::linear_rgb(0.5, 0.75, 1.0), Tile::ElevatedFloor),
-        // Lighter Blue == Ramp
-        (Color::linear_rgb(186. / 255., 221. / 255., 1.), Tile::Ramp),
-        // === Hallways ===
-        // Light Grey == Connecting Hallway Floor
-        (Color::linear_rgb(0.75, 0.75, 0.75), Tile::Hallway(0)),
-        // Light Medium Grey == Connecting Hallway Floor
-        (Color::linear_rgb(0.625, 0.625, 0.625), Tile::Hallway(1)),
-        // Medium Grey == Connecting Hallway Floor
-        (Color::linear_rgb(0.5, 0.5, 0.5), Tile::Hallway(2)),
-        // Black == Wall
-        (Color::linear_rgb(0., 0., 0.), Tile::Wall),
-        // Green == Computer Mainframe
-        (Color::linear_rgb(0., 1., 0.), Tile::ComputerMainframe),
-        // Green == Computer Mainframe
-        (
-            Color::linear_rgb(101. / 255., 1., 0.),
-            Tile::ComputerMainframeBig,
-        ),
-        // Light Blue == Outside
-        (Color::linear_rgb(0.5, 0.5, 0.835), Tile::Outside),
-        // Blue == Zappy
-        (Color::linear_rgb(0., 0., 1.), Tile::Zappy),
-        // Dark Grey == Well
-        (Color::linear_rgb(0.25, 0.25, 0.25), Tile::Well),
-        // Purple == Door
-        (Color::linear_rgb(0.5, 0.0, 1.0), Tile::Door),
-        // Orange == Crate
-        (Color::linear_rgb(1., 0.5, 0.0), Tile::Crate),
-        // Pink == Laser Source
-        (Color::linear_rgb(1., 0.5, 0.5), Tile::LaserSource),
-        // Red == Player Start
-        (Color::linear_rgb(1., 0., 0.), Tile::PlayerStart),
-        // Yellow == Save/Spawn Point
-        (Color::linear_rgb(1., 1., 0.), Tile::SpawnPoint),
-        // Magenta == Zipline
-        (Color::linear_rgb(1., 0., 1.), Tile::Zipline),
-        // Dark Magenta == Zipline without floor
-        (Color::linear_rgb(0.5, 0., 0.5), Tile::ZiplineOverWell),
-        // Brown == Chain
-        (
-            Color::linear_rgb(159.0 / 255.0, 113.0 / 255.0, 62.0 / 255.0),
-            Tile::Chain,
-        ),
-        // Pale Purple == Electricity Outlet
-        (
-            Color::linear_rgb(158.0 / 255.0, 86.0 / 255.0, 158.0 / 255.0),
-            Tile::Outlet,
-        ),
-        // Light Teal == Power Source
-        (
-            Color::linear_rgb(128. / 255., 255. / 255., 221. / 255.),
-            Tile::PowerSource,
-        ),
-        // Dark Teal == Floor Wire
-        (
-            Color::linear_rgb(66.0 / 255.0, 130.0 / 255.0, 111.0 / 255.0),
-            Tile::FloorWire,
-        ),
-        // Yellowish Teal == Cross Floor Wire
-        (
-            Color::linear_rgb(143.0 / 255.0, 178.0 / 255.0, 111.0 / 255.0),
-            Tile::CrossFloorWire,
-        ),
-        // Darker Teal == Wall Wire
-        (
-            Color::linear_rgb(51.0 / 255.0, 109.0 / 255.0, 136.0 / 255.0),
-            Tile::WallWire,
-        ),
-        // Ruby
-        (
-            Color::linear_rgb(201.0 / 255.0, 70.0 / 255.0, 174.0 / 255.0),
-            Tile::Ruby,
-        ),
-    ];
-
-    let color_distance_scale = 10_000;
+/// Queues the level on the other side of a hallway to spawn, and blocks off the hallway until it
+/// is ready.
+fn start_loading_neighbor(
+    commands: &mut Commands,
+    spawn_queue: &mut SpawnQueue,
+    active_levels: &mut ActiveLevels,
+    common: &Common,
+    levels: &Levels,
+    parsed_levels: &ParsedLevels,
+    from: &LevelName,
+    to: &LevelName,
+    pattern: &HallwayPattern,
+) {
+    let from_shift = active_levels.get(from).unwrap().shift;
+    let shift = parsed_levels.hallway_shift(from, from_shift, to, pattern);
+
+    spawn_queue.cancel(to);
+    load_level(
+        shift,
+        LevelTag { level: to.clone() },
+        &mut spawn_queue.for_level(to),
+        common,
+        &levels.levels[to],
+        &parsed_levels.levels[to],
+        false,
+        &parsed_levels.junction_to_levels,
+    );
+    active_levels.set_state(to, LevelState::Loading, shift);
 
-    let (color_distance, candidate) = MAPPING
+    // Block off the far side of the hallway, where the new level's floor will be.
+    let from_level = &parsed_levels.levels[from];
+    let junction = from_level
+        .hallways
         .iter()
-        .map(|candidate| (candidate.0.distance(color), candidate))
-        .min_by_key(|a| (a.0 * color_distance_scale as f32) as i64)
-        .unwrap();
-
-    if color_distance > 0.1 {
-        eprintln!("unknown color {:?}", color);
-        return None;
+        .find(|h| h.pattern == *pattern)
+        .expect("hallway must exist in the level it was spawned from");
+    let mut blocked: HashSet<IVec2> = HashSet::new();
+    for &p in junction.grids.iter() {
+        for d in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            let q = p + d;
+            if junction.grids.contains(&q)
+                || from_level.tile(q) != Tile::Outside
+                || !blocked.insert(q)
+            {
+                continue;
+            }
+            commands.spawn((
+                LevelTag {
+                    level: from.clone(),
+                },
+                Mesh3d(common.mesh_cube.clone()),
+                MeshMaterial3d(common.material_invisible.clone()),
+                Transform::from_translation(from_shift + Vec3::new(q.x as f32, 1.5, q.y as f32))
+                    .with_scale(Vec3::new(1., 2., 1.)),
+                RigidBody::Static,
+                Collider::cuboid(1., 1., 1.),
+                HallwayBlocker { level: to.clone() },
+                DoesNotClearFog,
+            ));
+        }
     }
-
-    Some(candidate.1)
 }
 
-fn is_electrical(tile: &Tile) -> bool {
-    matches!(
-        tile,
-        Tile::FloorWire
-            | Tile::CrossFloorWire
-            | Tile::WallWire
-            | Tile::PowerSource
-            | Tile::Outlet
-            | Tile::ComputerMainframe
-            | Tile::ComputerMainframeBig
-            | Tile::Door
-            | Tile::Zappy
-    )
+/// Opens up hallways once the level on the other side has finished spawning.
+fn remove_hallway_blockers_system(
+    mut commands: Commands,
+    blockers: Query<(Entity, &HallwayBlocker)>,
+    active_levels: Res<ActiveLevels>,
+) {
+    for (entity, blocker) in blockers.iter() {
+        if active_levels.state(&blocker.level) != LevelState::Loading {
+            commands.entity(entity).despawn();
+        }
+    }
 }
 
-fn spawn_text(commands: &mut Commands, shift: Vec3, level_tag: &LevelTag, common: &Common) {
-    if level_tag.is_level("level_1.png") {
-        commands.spawn((
-            level_tag.clone(),
-            Mesh3d(common.mesh_plane.clone()),
-            MeshMaterial3d(common.material_tutorial_move.clone()),
-            Transform::from_translation(shift + Vec3::new(36.0, 5.0, 9.0))
-                .looking_to(Vec3::Y + Vec3::Z * 0.9, -Vec3::Y)
-                .with_scale(Vec3::splat(6.)),
-            DoesNotClearFog,
-        ));
+/// Finds the levels on either side of each hallway pattern, panicking if any can't be paired.
+fn get_junction_levels(
+    parsed_levels: &HashMap<LevelName, ParsedLevel>,
+) -> HashMap<HallwayPattern, Vec<LevelName>> {
+    match pair_hallways(parsed_levels.iter()) {
+        Ok(junction_to_levels) => junction_to_levels,
+        Err(errors) => {
+            for err in errors.iter() {
+                error!("{err}");
+            }
+            panic!("{} hallways could not be paired", errors.len());
+        }
+    }
+}
 
-        commands.spawn((
+fn spawn_signs(
+    commands: &mut LevelCommands,
+    shift: Vec3,
+    level_tag: &LevelTag,
+    common: &Common,
+    level: &LevelInfo,
+) {
+    for sign in level.signs.iter() {
+        let bundle = (
             level_tag.clone(),
             Mesh3d(common.mesh_plane.clone()),
-            MeshMaterial3d(common.material_tutorial_interact.clone()),
-            Transform::from_translation(shift + Vec3::new(28.0, 5.0, 9.0))
-                .looking_to(Vec3::Y + Vec3::Z * 0.9, -Vec3::Y)
-                .with_scale(Vec3::splat(6.)),
+            MeshMaterial3d(sign.material.clone()),
+            Transform::from_translation(shift + sign.position)
+                .looking_to(sign.facing, sign.up)
+                .with_scale(Vec3::splat(sign.scale)),
             DoesNotClearFog,
-        ));
+        );
+        match sign.appear {
+            SignAppear::Always => commands.spawn(bundle),
+            SignAppear::RubyCollected => {
+                commands.spawn((bundle, Visibility::Hidden, ShowWhenRubyCollected))
+            }
+        }
     }
 }
 
 fn load_level(
     shift: Vec3,
     level_tag: LevelTag,
-    commands: &mut Commands,
+    commands: &mut LevelCommands,
     common: &Common,
-    image: &Image,
+    level: &LevelInfo,
+    parsed: &ParsedLevel,
     should_spawn_player: bool,
     junction_to_levels: &HashMap<HallwayPattern, Vec<LevelName>>,
 ) {
-    spawn_text(commands, shift, &level_tag, common);
+    spawn_signs(commands, shift, &level_tag, common, level);
 
     struct LevelSpawner<'a> {
-        spawn: Box<dyn FnMut(&mut Commands, &SpawnInfo) + 'a>,
+        spawn: Box<dyn FnMut(&mut LevelCommands, &SpawnInfo) + 'a>,
         skip_floor: bool,
         lift_entity: bool,
         lift_floor: bool,
@@ -596,7 +827,7 @@ fn load_level(
         fn for_tile(self, tile: Tile) -> (Tile, Self) {
             (tile, self)
         }
-        fn new(spawn: impl FnMut(&mut Commands, &SpawnInfo) + 'a) -> Self {
+        fn new(spawn: impl FnMut(&mut LevelCommands, &SpawnInfo) + 'a) -> Self {
             Self {
                 spawn: Box::new(spawn),
                 skip_floor: false,
@@ -624,47 +855,271 @@ fn load_level(
                 lift_floor: false,
                 ..self
             }
-        }
-    }
+        }
+    }
+
+    // Floors and walls are merged into larger blocks once the whole level has been visited.
+    let blocks: Mutex<HashMap<Handle<StandardMaterial>, HashSet<IVec3>>> =
+        Mutex::new(HashMap::default());
+    let add_block = |p: Vec3, material: Handle<StandardMaterial>| {
+        blocks
+            .lock()
+            .unwrap()
+            .entry(material)
+            .or_default()
+            .insert((p - shift).round().as_ivec3());
+    };
+
+    let zipline_positions: Mutex<HashMap<IVec2, Vec3>> = Mutex::new(HashMap::default());
+    let chains: Mutex<HashMap<IVec2, Vec3>> = Mutex::new(HashMap::default());
+
+    for (_, color) in parsed.grid.unknown.iter() {
+        error!("unknown color {color:?} in {}", level_tag.level);
+    }
+
+    let pin = |p: IVec2, direction: IVec2| GatePin {
+        direction,
+        channel: parsed.channel_towards(p, direction),
+    };
+    let spawn_gate = |commands: &mut LevelCommands, info: &SpawnInfo| {
+        let Tile::Gate(kind) = parsed.tile(info.grid) else {
+            unreachable!("gates are only spawned for gate tiles");
+        };
+        let sides = &parsed.gates[&info.grid];
+        let [output] = sides.outputs[..] else {
+            error!(
+                "{kind:?} gate at {} in {} must have exactly one gate output next to it, so it \
+                 was not spawned",
+                info.grid, level_tag.level
+            );
+            return;
+        };
+        let mesh = match kind {
+            GateKind::And => &common.mesh_cube,
+            GateKind::Or => &common.mesh_cylinder,
+            GateKind::Not => &common.mesh_small_sphere,
+            GateKind::Xor => &common.mesh_sphere,
+        };
+        commands.spawn((
+            level_tag.clone(),
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(common.material_dark_blue.clone()),
+            Transform::from_translation(info.pos + Vec3::Y * 0.7).with_scale(Vec3::splat(0.6)),
+            RigidBody::Static,
+            Collider::cuboid(1.0, 1.0, 1.0),
+            LogicGate {
+                kind,
+                inputs: sides.inputs.iter().map(|&d| pin(info.grid, d)).collect(),
+                output: pin(info.grid, output),
+            },
+            parsed.layer(info.grid),
+        ));
+    };
+
+    let spawn_pressure_plate = |commands: &mut LevelCommands, info: &SpawnInfo| {
+        let Tile::PressurePlate(mode) = parsed.tile(info.grid) else {
+            unreachable!("pressure plates are only spawned for pressure plate tiles");
+        };
+        commands.spawn((
+            level_tag.clone(),
+            Mesh3d(common.mesh_cube.clone()),
+            MeshMaterial3d(common.material_dark_blue.clone()),
+            Transform::from_translation(info.pos + Vec3::Y * 0.55)
+                .with_scale(Vec3::new(0.9, 0.1, 0.9)),
+            // Tall enough to reach into anything resting on the plate.
+            Collider::cuboid(1.0, 4.0, 1.0),
+            PressurePlate {
+                mode,
+                min_mass: level
+                    .tile_settings
+                    .get(&info.grid)
+                    .and_then(|settings| settings.min_mass),
+                pressed: false,
+            },
+            parsed.channel(info.grid),
+            parsed.layer(info.grid),
+        ));
+    };
+
+    let spawn_switch = |commands: &mut LevelCommands, info: &SpawnInfo| {
+        let Tile::Switch(kind) = parsed.tile(info.grid) else {
+            unreachable!("switches are only spawned for switch tiles");
+        };
+        let facing = parsed.facing[&info.grid];
+        let facing = Vec3::new(facing.x as f32, 0.0, facing.y as f32);
+        // Switches backed by a wall are mounted on it, and the rest are levers on the floor.
+        let transform = if parsed.is_wall(info.grid - parsed.facing[&info.grid]) {
+            Transform::from_translation(info.pos + Vec3::Y * 1.3 - facing * 0.45)
+                .looking_to(facing, Vec3::Y)
+                .with_scale(Vec3::new(0.4, 0.5, 0.15))
+        } else {
+            Transform::from_translation(info.pos + Vec3::Y * 0.9)
+                .looking_to(facing, Vec3::Y)
+                .with_scale(Vec3::new(0.25, 0.8, 0.25))
+        };
+        let duration = level
+            .tile_settings
+            .get(&info.grid)
+            .and_then(|settings| settings.duration)
+            .unwrap_or(DEFAULT_BUTTON_DURATION);
+        commands.spawn((
+            level_tag.clone(),
+            Mesh3d(common.mesh_cube.clone()),
+            MeshMaterial3d(common.material_dark_blue.clone()),
+            transform,
+            RigidBody::Static,
+            Collider::cuboid(1.0, 1.0, 1.0),
+            Switch::new(kind, duration),
+            parsed.channel(info.grid),
+            parsed.layer(info.grid),
+            children![(
+                Mesh3d(common.mesh_cube.clone()),
+                MeshMaterial3d(common.material_electricity.clone()),
+                Transform::from_translation(Vec3::Y * 0.65).with_scale(Vec3::new(0.0, 0.15, 1.2)),
+                SwitchTimerBar,
+            )],
+        ));
+    };
+
+    let spawn_diode = |commands: &mut LevelCommands, info: &SpawnInfo| {
+        let Tile::Diode(direction) = parsed.tile(info.grid) else {
+            unreachable!("diodes are only spawned for diode tiles");
+        };
+        let offset = direction.offset();
+        let arrow_part = |translation: Vec3, angle: f32, length: f32| {
+            (
+                Mesh3d(common.mesh_cube.clone()),
+                MeshMaterial3d(common.material_yellow.clone()),
+                Transform::from_translation(translation)
+                    .with_rotation(Quat::from_rotation_y(angle))
+                    .with_scale(Vec3::new(0.12, 0.1, length)),
+            )
+        };
+        // The arrow points along its local -Z, towards the wire it powers.
+        let head = std::f32::consts::FRAC_PI_4;
+        commands.spawn((
+            level_tag.clone(),
+            Transform::from_translation(info.pos + Vec3::Y * 0.55)
+                .looking_to(Vec3::new(offset.x as f32, 0.0, offset.y as f32), Vec3::Y),
+            Visibility::default(),
+            Diode { direction },
+            LogicGate {
+                kind: GateKind::Or,
+                inputs: vec![pin(info.grid, -offset)],
+                output: pin(info.grid, offset),
+            },
+            parsed.layer(info.grid),
+            children![
+                arrow_part(Vec3::Z * 0.05, 0.0, 0.6),
+                arrow_part(Vec3::new(0.124, 0.0, -0.226), head, 0.35),
+                arrow_part(Vec3::new(-0.124, 0.0, -0.226), -head, 0.35),
+            ],
+        ));
+    };
+
+    let spawn_channel_wire = |commands: &mut LevelCommands, info: &SpawnInfo| {
+        spawn_floor_wire(commands, common, parsed, info.grid, &level_tag, info.pos);
+    };
+
+    let spawn_laser_source = |commands: &mut LevelCommands, info: &SpawnInfo| {
+        // Wall
+        add_block(info.pos + Vec3::Y, common.material_dark_gray.clone());
+        add_block(info.pos + Vec3::Y * 2., common.material_invisible.clone());
+
+        // Fire a laser onto each floor next to the source.
+        let directions: Vec<IVec2> = [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y]
+            .into_iter()
+            .filter(|&d| parsed.tile(info.grid + d) == Tile::Floor)
+            .collect();
+        let power = (parsed.tile(info.grid) == Tile::PoweredLaserSource)
+            .then(|| (parsed.channel(info.grid), parsed.layer(info.grid)));
+        let level_tag = level_tag.clone();
+        let common = common.clone();
+        let pos = info.pos;
+        commands.queue(directions.len() + 1, move |commands| {
+            let emitter = power.map(|(channel, layer)| {
+                commands
+                    .spawn((
+                        level_tag.clone(),
+                        Transform::from_translation(pos + Vec3::Y),
+                        LaserEmitter,
+                        channel,
+                        layer,
+                    ))
+                    .id()
+            });
+            for d in directions {
+                commands.spawn((
+                    level_tag.clone(),
+                    Mesh3d(common.mesh_cube.clone()),
+                    MeshMaterial3d(common.material_orange.clone()),
+                    Transform::from_translation(
+                        pos + Vec3::Y + Vec3::new(d.x as f32, 0.0, d.y as f32) * 0.5,
+                    )
+                    .with_scale(Vec3::splat(0.5)),
+                    Laser {
+                        direction: Vec3::new(d.x as f32, 0.0, d.y as f32),
+                        beams: Vec::new(),
+                        emitter,
+                    },
+                ));
+            }
+        });
+    };
 
-    let spawn_cube = |commands: &mut Commands, p: Vec3, material: Handle<StandardMaterial>| {
+    let spawn_mirror = |commands: &mut LevelCommands, info: &SpawnInfo| {
+        let (Tile::Mirror(slant) | Tile::Splitter(slant)) = parsed.tile(info.grid) else {
+            unreachable!("mirrors are only spawned for mirror and splitter tiles");
+        };
+        let splits = matches!(parsed.tile(info.grid), Tile::Splitter(_));
+        let material = if splits {
+            &common.material_splitter
+        } else {
+            &common.material_mirror
+        };
         commands.spawn((
             level_tag.clone(),
             Mesh3d(common.mesh_cube.clone()),
-            MeshMaterial3d(material),
-            Transform::from_translation(p),
+            MeshMaterial3d(material.clone()),
+            Transform::from_translation(info.pos + Vec3::Y)
+                .with_rotation(slant.rotation())
+                .with_scale(Vec3::new(0.1, 1.0, 1.3)),
             RigidBody::Static,
-            Collider::cuboid(1., 1., 1.),
+            Collider::cuboid(1.0, 1.0, 1.0),
+            Mirror { splits },
         ));
     };
 
-    let spawn_floor = |commands: &mut Commands, p: Vec3| {
-        spawn_cube(commands, p, common.material_gray.clone());
+    let capacity = |p: IVec2| {
+        level
+            .tile_settings
+            .get(&p)
+            .and_then(|settings| settings.capacity)
+            .unwrap_or(DEFAULT_CAPACITY)
     };
-
-    let zipline_positions: Mutex<HashMap<IVec2, Vec3>> = Mutex::new(HashMap::default());
-    let chains: Mutex<HashMap<IVec2, Vec3>> = Mutex::new(HashMap::default());
-
-    let is_raised = |p: IVec2| -> bool {
-        let c = image.get_color_at(p.x as u32, p.y as u32).unwrap();
-
-        c.distance(&Color::linear_rgb(186. / 255., 221. / 255., 1.)) < 0.1
-            || c.distance(&Color::linear_rgb(0.5, 0.75, 1.0)) < 0.1
+    let charge_level = || {
+        (
+            Mesh3d(common.mesh_cylinder.clone()),
+            MeshMaterial3d(common.material_electricity.clone()),
+            Transform::from_scale(Vec3::new(1.05, 0.0, 1.05)),
+            ChargeLevel,
+        )
     };
 
-    let mut tile_grid: HashMap<IVec2, Tile> = HashMap::new();
-    for x in 0..image.width() {
-        for z in 0..image.height() {
-            let color = image
-                .get_color_at(x, z)
-                .expect("must be able to get color in image");
-            let tile = color_to_tile(&color);
-            tile_grid.insert(
-                IVec2::new(x as i32, z as i32),
-                tile.unwrap_or(Tile::Outside),
+    let link = |p: IVec2| {
+        let link = level
+            .tile_settings
+            .get(&p)
+            .and_then(|settings| settings.link.clone());
+        if link.is_none() {
+            error!(
+                "{:?} at {p} in {} has no `link` in its tile settings, so it is not paired",
+                parsed.tile(p),
+                level_tag.level
             );
         }
-    }
+        link
+    };
 
     #[allow(clippy::eq_op)]
     let mut color_spawners: HashMap<Tile, LevelSpawner> = [
@@ -674,8 +1129,8 @@ fn load_level(
         })
         .for_tile(Tile::Floor),
         // Light Blue == Elevated Floor
-        LevelSpawner::new(|commands, info| {
-            spawn_cube(commands, info.pos + Vec3::Y, common.material_gray.clone());
+        LevelSpawner::new(|_commands, info| {
+            add_block(info.pos + Vec3::Y, common.material_gray.clone());
         })
         .for_tile(Tile::ElevatedFloor),
         // Lighter Blue == Ramp
@@ -710,56 +1165,14 @@ fn load_level(
         })
         .for_tile(Tile::Hallway(2)),
         // Black == Wall
-        LevelSpawner::new(|commands, info| {
-            spawn_cube(
-                commands,
-                info.pos + Vec3::Y,
-                common.material_dark_gray.clone(),
-            );
-            spawn_cube(
-                commands,
-                info.pos + Vec3::Y * 2.,
-                common.material_invisible.clone(),
-            );
+        LevelSpawner::new(|_commands, info| {
+            add_block(info.pos + Vec3::Y, common.material_dark_gray.clone());
+            add_block(info.pos + Vec3::Y * 2., common.material_invisible.clone());
         })
         .for_tile(Tile::Wall),
         // Green == Compute
         LevelSpawner::new(|commands, info| {
-            // Which way should it face?
-            fn is_floor(t: Tile) -> bool {
-                matches!(
-                    t,
-                    Tile::Floor
-                        | Tile::FloorWire
-                        | Tile::CrossFloorWire
-                        | Tile::Outlet
-                        | Tile::Zipline
-                )
-            }
-
-            fn is_wall(t: Tile) -> bool {
-                matches!(t, Tile::Wall | Tile::WallWire)
-            }
-
-            let candidates = [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y];
-
-            let facing_direction = candidates
-                .iter()
-                .max_by_key(|&direction| {
-                    let mut score = 0;
-                    let forward = info.grid + direction;
-                    if is_floor(tile_grid[&forward]) {
-                        score += 999;
-                    }
-                    if tile_grid[&forward] == Tile::Crate {
-                        score += 500;
-                    }
-                    if is_wall(tile_grid[&(info.grid - direction)]) {
-                        score += 100;
-                    }
-                    score
-                })
-                .unwrap();
+            let facing_direction = parsed.facing[&info.grid];
 
             commands.spawn((
                 level_tag.clone(),
@@ -775,46 +1188,14 @@ fn load_level(
                     has_charge: false,
                     location: info.grid,
                 },
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
             ));
         })
         .lift_floor()
         .for_tile(Tile::ComputerMainframe),
         LevelSpawner::new(|commands, info| {
-            // Which way should it face?
-            fn is_floor(t: Tile) -> bool {
-                matches!(
-                    t,
-                    Tile::Floor
-                        | Tile::FloorWire
-                        | Tile::CrossFloorWire
-                        | Tile::Outlet
-                        | Tile::Zipline
-                )
-            }
-
-            fn is_wall(t: Tile) -> bool {
-                matches!(t, Tile::Wall | Tile::WallWire)
-            }
-
-            let candidates = [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y];
-
-            let facing_direction = candidates
-                .iter()
-                .max_by_key(|&direction| {
-                    let mut score = 0;
-                    let forward = info.grid + direction;
-                    if is_floor(tile_grid[&forward]) {
-                        score += 999;
-                    }
-                    if tile_grid[&forward] == Tile::Crate {
-                        score += 500;
-                    }
-                    if is_wall(tile_grid[&(info.grid - direction)]) {
-                        score += 100;
-                    }
-                    score
-                })
-                .unwrap();
+            let facing_direction = parsed.facing[&info.grid];
 
             commands.spawn((
                 level_tag.clone(),
@@ -832,6 +1213,8 @@ fn load_level(
                     has_charge: false,
                     location: info.grid,
                 },
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
                 WinMainframe,
                 Interactible::radius(6.)
                     .with_priority(4)
@@ -848,32 +1231,38 @@ fn load_level(
         .for_tile(Tile::Outside),
         // Blue == Zappy
         LevelSpawner::new(|commands, info| {
-            spawn_floor_wire(
-                commands, common, &tile_grid, &level_tag, info.grid, info.pos,
+            spawn_floor_wire(commands, common, parsed, info.grid, &level_tag, info.pos);
+            let robot = (
+                level_tag.clone(),
+                Mesh3d(common.mesh_sphere.clone()),
+                MeshMaterial3d(common.material_beepboop.clone()),
+                Transform::from_translation(info.pos + Vec3::Y),
+                RigidBody::Static,
+                Collider::cuboid(1., 1., 1.),
+                EvilRobot { has_charge: false },
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
             );
-            commands
-                .spawn((
-                    level_tag.clone(),
-                    Mesh3d(common.mesh_sphere.clone()),
-                    MeshMaterial3d(common.material_beepboop.clone()),
-                    Transform::from_translation(info.pos + Vec3::Y),
-                    RigidBody::Static,
-                    Collider::cuboid(1., 1., 1.),
-                    EvilRobot { has_charge: false },
-                ))
-                .with_child((
+            let fields = [
+                (
                     Mesh3d(common.mesh_sphere.clone()),
                     MeshMaterial3d(common.material_zappy_field.clone()),
                     Transform::from_scale(Vec3::splat(2.)),
                     Spinning(Vec3::new(1., 1., 1.)),
-                ))
-                .with_child((
+                ),
+                (
                     Mesh3d(common.mesh_sphere.clone()),
                     MeshMaterial3d(common.material_zappy_field.clone()),
                     Transform::from_scale(Vec3::splat(1.9)),
                     Spinning(Vec3::new(-0.6, -0.6, -0.6)),
-                ));
+                ),
+            ];
+            commands.queue(3, move |commands| {
+                let [outer, inner] = fields;
+                commands.spawn(robot).with_child(outer).with_child(inner);
+            });
         })
+        .lift_floor()
         .for_tile(Tile::Zappy),
         // Dark Grey == Well
         LevelSpawner::new(|commands, info| {
@@ -903,6 +1292,8 @@ fn load_level(
                     closed_at: info.pos + Vec3::Y,
                 },
                 Wire,
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
             ));
             // Door base
             commands.spawn((
@@ -949,44 +1340,9 @@ fn load_level(
         .lift_floor()
         .for_tile(Tile::Crate),
         // Pink == Laser Source
-        LevelSpawner::new(|commands, info| {
-            // Wall
-            spawn_cube(
-                commands,
-                info.pos + Vec3::Y,
-                common.material_dark_gray.clone(),
-            );
-            spawn_cube(
-                commands,
-                info.pos + Vec3::Y * 2.,
-                common.material_invisible.clone(),
-            );
-
-            for d in [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y] {
-                let neighbor = info.grid + d;
-
-                let neighbor_color = tile_grid[&neighbor];
-
-                if neighbor_color == Tile::Floor {
-                    // Spawn laser in this direction
-                    commands.spawn((
-                        level_tag.clone(),
-                        Mesh3d(common.mesh_cube.clone()),
-                        MeshMaterial3d(common.material_orange.clone()),
-                        Transform::from_translation(
-                            info.pos + Vec3::Y + Vec3::new(d.x as f32, 0.0, d.y as f32) * 0.5,
-                        )
-                        .with_scale(Vec3::splat(0.5)),
-                        Laser {
-                            direction: Vec3::new(d.x as f32, 0.0, d.y as f32),
-                            beam: None,
-                        },
-                    ));
-                }
-            }
-        })
-        .lift_floor()
-        .for_tile(Tile::LaserSource),
+        LevelSpawner::new(spawn_laser_source)
+            .lift_floor()
+            .for_tile(Tile::LaserSource),
         // Red == Player
         LevelSpawner::new(|commands, info| {
             if !should_spawn_player {
@@ -1072,8 +1428,11 @@ fn load_level(
                 RigidBody::Static,
                 Collider::cuboid(1.0, 1.0, 1.0),
                 Outlet { plug: None },
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
             ));
         })
+        .lift_floor()
         .for_tile(Tile::Outlet),
         // Light Teal == Power Source
         LevelSpawner::new(|commands, info| {
@@ -1086,105 +1445,104 @@ fn load_level(
                 RigidBody::Static,
                 Collider::cylinder(0.5, 1.0),
                 PowerSource,
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
             ));
         })
+        .lift_floor()
         .for_tile(Tile::PowerSource),
         LevelSpawner::new(|commands, info| {
-            spawn_floor_wire(
-                commands, common, &tile_grid, &level_tag, info.grid, info.pos,
-            );
+            spawn_floor_wire(commands, common, parsed, info.grid, &level_tag, info.pos);
         })
+        .lift_floor()
         .for_tile(Tile::FloorWire),
         LevelSpawner::new(|commands, info| {
-            spawn_floor_wire(
-                commands, common, &tile_grid, &level_tag, info.grid, info.pos,
-            );
+            spawn_floor_wire(commands, common, parsed, info.grid, &level_tag, info.pos);
 
             // Spawn wires in any direction that meets another cross wire.
-            for dir in [IVec2::X, IVec2::Y] {
-                let mut dist = 1;
-                let mut must_cross = false;
-                loop {
-                    let at = info.grid + dir * dist;
-
-                    if dist > 6 {
-                        break;
-                    }
-
-                    match tile_grid[&at] {
-                        Tile::Wall | Tile::Outside => {
-                            break;
-                        }
-                        Tile::CrossFloorWire => {
-                            must_cross = true;
-                            break;
-                        }
-                        _ if is_electrical(&tile_grid[&at]) => {
-                            // A non-crossing thing.
-                            break;
-                        }
-                        _ => {
-                            // Do nothing
-                        }
-                    }
-                    dist += 1;
-                }
-                if must_cross {
-                    spawn_floor_wire_segment(commands, common, &level_tag, info.pos, dir);
-
-                    println!("must cross {:?} at dist {:?}", dir, dist);
-                    for i in 1..dist {
-                        let spawn_at = info.grid + dir * i;
-                        println!(" i = {i}, spawn_at = {:?}", spawn_at);
-                        spawn_floor_wire(
-                            commands,
-                            common,
-                            &tile_grid,
-                            &level_tag,
-                            info.grid + dir * i,
-                            info.pos + dir.as_vec2().extend(0.0).xzy() * i as f32,
-                        );
-                    }
+            for crossing in parsed.wire_crossings.iter().filter(|c| c.from == info.grid) {
+                let dir = crossing.direction;
+                spawn_floor_wire_segment(
+                    commands,
+                    common,
+                    &level_tag,
+                    info.pos,
+                    dir,
+                    Channel::Plain,
+                    parsed.layer(info.grid),
+                );
+
+                for i in 1..crossing.length {
+                    let spawn_at = info.grid + dir * i;
+                    spawn_floor_wire(
+                        commands,
+                        common,
+                        parsed,
+                        spawn_at,
+                        &level_tag,
+                        info.pos + dir.as_vec2().extend(0.0).xzy() * i as f32,
+                    );
                 }
             }
         })
+        .lift_floor()
         .for_tile(Tile::CrossFloorWire),
         LevelSpawner::new(|commands, info| {
-            spawn_cube(
-                commands,
-                info.pos + Vec3::Y,
-                common.material_dark_gray.clone(),
-            );
-            spawn_cube(
-                commands,
-                info.pos + Vec3::Y * 2.,
-                common.material_invisible.clone(),
-            );
-
-            for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-                let neighbor = &tile_grid[&(info.grid + IVec2::new(dx, dz))];
-                if is_electrical(neighbor) {
-                    let center = info.pos + Vec3::Y * 1.5;
-                    let shift = Vec3::new(dx as f32, 0., dz as f32) * WIRE_EXTENT / 2.;
+            add_block(info.pos + Vec3::Y, common.material_dark_gray.clone());
+            add_block(info.pos + Vec3::Y * 2., common.material_invisible.clone());
 
-                    let mut scale = Vec3::new(WIRE_WIDTH, 0.2, WIRE_WIDTH);
-                    if dx != 0 {
-                        scale.x = WIRE_EXTENT + WIRE_WIDTH;
-                    } else {
-                        scale.z = WIRE_EXTENT + WIRE_WIDTH;
-                    }
+            for &d in parsed.wires[&info.grid].iter() {
+                let center = info.pos + Vec3::Y * 1.5;
+                let shift = Vec3::new(d.x as f32, 0., d.y as f32) * WIRE_EXTENT / 2.;
 
-                    commands.spawn((
-                        level_tag.clone(),
-                        Mesh3d(common.mesh_cube.clone()),
-                        MeshMaterial3d(common.material_electricity.clone()),
-                        Transform::from_translation(center + shift).with_scale(scale),
-                        Wire,
-                    ));
+                let mut scale = Vec3::new(WIRE_WIDTH, 0.2, WIRE_WIDTH);
+                if d.x != 0 {
+                    scale.x = WIRE_EXTENT + WIRE_WIDTH;
+                } else {
+                    scale.z = WIRE_EXTENT + WIRE_WIDTH;
                 }
+
+                commands.spawn((
+                    level_tag.clone(),
+                    Mesh3d(common.mesh_cube.clone()),
+                    MeshMaterial3d(common.material_dark_blue.clone()),
+                    Transform::from_translation(center + shift).with_scale(scale),
+                    Wire,
+                    parsed.channel(info.grid),
+                    parsed.layer(info.grid),
+                ));
             }
         })
         .for_tile(Tile::WallWire),
+        LevelSpawner::new(|commands, info| {
+            // A post up to the elevated floors, with a part on each layer it joins.
+            for layer in parsed.layers(info.grid) {
+                commands.spawn((
+                    level_tag.clone(),
+                    Mesh3d(common.mesh_cube.clone()),
+                    MeshMaterial3d(common.material_dark_blue.clone()),
+                    Transform::from_translation(info.pos + Vec3::Y * (0.75 + 0.5 * layer as f32))
+                        .with_scale(Vec3::new(WIRE_WIDTH, 0.5, WIRE_WIDTH)),
+                    Wire,
+                    parsed.channel(info.grid),
+                    GridLayer(layer),
+                ));
+            }
+            // Each arm runs at the height of the wire it leads to.
+            for &dir in parsed.wires[&info.grid].iter() {
+                let layer = parsed.layer(info.grid + dir);
+                spawn_floor_wire_segment(
+                    commands,
+                    common,
+                    &level_tag,
+                    info.pos + Vec3::Y * layer.0 as f32,
+                    dir,
+                    parsed.channel(info.grid),
+                    layer,
+                );
+            }
+        })
+        .for_tile(Tile::VerticalWire),
         LevelSpawner::new(|commands, info| {
             commands.spawn((
                 level_tag.clone(),
@@ -1199,13 +1557,217 @@ fn load_level(
             ));
         })
         .for_tile(Tile::Ruby),
+        LevelSpawner::new(spawn_gate)
+            .lift_floor()
+            .for_tile(Tile::Gate(GateKind::And)),
+        LevelSpawner::new(spawn_gate)
+            .lift_floor()
+            .for_tile(Tile::Gate(GateKind::Or)),
+        LevelSpawner::new(spawn_gate)
+            .lift_floor()
+            .for_tile(Tile::Gate(GateKind::Not)),
+        LevelSpawner::new(spawn_gate)
+            .lift_floor()
+            .for_tile(Tile::Gate(GateKind::Xor)),
+        LevelSpawner::new(|commands, info| {
+            spawn_floor_wire(commands, common, parsed, info.grid, &level_tag, info.pos);
+        })
+        .lift_floor()
+        .for_tile(Tile::GateOutput),
+        LevelSpawner::new(spawn_pressure_plate)
+            .lift_floor()
+            .for_tile(Tile::PressurePlate(PlateMode::Source)),
+        LevelSpawner::new(spawn_pressure_plate)
+            .lift_floor()
+            .for_tile(Tile::PressurePlate(PlateMode::Bridge)),
+        LevelSpawner::new(spawn_switch)
+            .lift_floor()
+            .for_tile(Tile::Switch(SwitchKind::Toggle)),
+        LevelSpawner::new(spawn_switch)
+            .lift_floor()
+            .for_tile(Tile::Switch(SwitchKind::Timed)),
+        LevelSpawner::new(spawn_diode)
+            .lift_floor()
+            .for_tile(Tile::Diode(DiodeDirection::Right)),
+        LevelSpawner::new(spawn_diode)
+            .lift_floor()
+            .for_tile(Tile::Diode(DiodeDirection::Left)),
+        LevelSpawner::new(spawn_diode)
+            .lift_floor()
+            .for_tile(Tile::Diode(DiodeDirection::Down)),
+        LevelSpawner::new(spawn_diode)
+            .lift_floor()
+            .for_tile(Tile::Diode(DiodeDirection::Up)),
+        LevelSpawner::new(spawn_channel_wire)
+            .lift_floor()
+            .for_tile(Tile::ChannelWire(Channel::Red)),
+        LevelSpawner::new(spawn_channel_wire)
+            .lift_floor()
+            .for_tile(Tile::ChannelWire(Channel::Green)),
+        LevelSpawner::new(spawn_channel_wire)
+            .lift_floor()
+            .for_tile(Tile::ChannelWire(Channel::Blue)),
+        LevelSpawner::new(spawn_channel_wire)
+            .lift_floor()
+            .for_tile(Tile::ChannelCrossing),
+        LevelSpawner::new(|commands, info| {
+            commands.spawn((
+                level_tag.clone(),
+                Mesh3d(common.mesh_cylinder.clone()),
+                MeshMaterial3d(common.material_dark_blue.clone()),
+                Transform::from_translation(info.pos + Vec3::Y)
+                    .with_scale(Vec3::new(0.6, 1.0, 0.6)),
+                RigidBody::Static,
+                Collider::cylinder(0.5, 1.0),
+                Capacitor::empty(capacity(info.grid)),
+                PowerPart::new(PowerRole::Conductor),
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
+                children![charge_level()],
+            ));
+        })
+        .lift_floor()
+        .for_tile(Tile::Capacitor),
+        LevelSpawner::new(|commands, info| {
+            let battery = (
+                level_tag.clone(),
+                Mesh3d(common.mesh_cylinder.clone()),
+                MeshMaterial3d(common.material_dark_blue.clone()),
+                Transform::from_translation(info.pos + Vec3::Y)
+                    .with_scale(Vec3::new(0.5, 0.7, 0.5)),
+                RigidBody::Dynamic,
+                ColliderDensity(0.2),
+                Collider::cylinder(0.5, 1.0),
+                Capacitor::full(capacity(info.grid)),
+                Battery,
+                Channel::Plain,
+                DespawnFalling,
+                children![charge_level()],
+            );
+            commands.queue(2, move |commands| {
+                let mut battery = commands.spawn(battery);
+                let id = battery.id();
+                battery.insert(Plug::new(id));
+            });
+        })
+        .lift_floor()
+        .for_tile(Tile::Battery),
+        LevelSpawner::new(|commands, info| {
+            let Some(link) = link(info.grid) else {
+                return;
+            };
+            commands.spawn((
+                level_tag.clone(),
+                Mesh3d(common.mesh_cylinder.clone()),
+                MeshMaterial3d(common.material_dark_blue.clone()),
+                Transform::from_translation(info.pos + Vec3::Y * 1.3)
+                    .with_scale(Vec3::new(0.2, 1.6, 0.2)),
+                RigidBody::Static,
+                Collider::cylinder(0.5, 1.0),
+                Transmitter { link },
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
+            ));
+        })
+        .lift_floor()
+        .for_tile(Tile::Transmitter),
+        LevelSpawner::new(|commands, info| {
+            let length = level
+                .tile_settings
+                .get(&info.grid)
+                .and_then(|settings| settings.length)
+                .unwrap_or(DEFAULT_REEL_LENGTH);
+            let level_tag = level_tag.clone();
+            let common = common.clone();
+            let pos = info.pos;
+            commands.queue(3, move |commands| {
+                spawn_cable_reel(&level_tag, commands, &common, pos, length);
+            });
+        })
+        .lift_floor()
+        .for_tile(Tile::CableReel),
+        LevelSpawner::new(|commands, info| {
+            let Some(link) = link(info.grid) else {
+                return;
+            };
+            commands.spawn((
+                level_tag.clone(),
+                Mesh3d(common.mesh_cylinder.clone()),
+                MeshMaterial3d(common.material_dark_blue.clone()),
+                Transform::from_translation(info.pos + Vec3::Y * 0.7)
+                    .with_scale(Vec3::new(0.8, 0.3, 0.8)),
+                RigidBody::Static,
+                Collider::cylinder(0.5, 1.0),
+                Receiver { link },
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
+            ));
+        })
+        .lift_floor()
+        .for_tile(Tile::Receiver),
+        LevelSpawner::new(spawn_laser_source)
+            .lift_floor()
+            .for_tile(Tile::PoweredLaserSource),
+        LevelSpawner::new(|commands, info| {
+            commands.spawn((
+                level_tag.clone(),
+                Mesh3d(common.mesh_cube.clone()),
+                MeshMaterial3d(common.material_dark_gray.clone()),
+                Transform::from_translation(info.pos + Vec3::Y).with_scale(Vec3::splat(0.8)),
+                RigidBody::Static,
+                Collider::cuboid(1.0, 1.0, 1.0),
+                LaserReceptor::default(),
+                parsed.channel(info.grid),
+                parsed.layer(info.grid),
+            ));
+        })
+        .lift_floor()
+        .for_tile(Tile::LaserReceptor),
+        LevelSpawner::new(spawn_mirror)
+            .lift_floor()
+            .for_tile(Tile::Mirror(MirrorSlant::Slash)),
+        LevelSpawner::new(spawn_mirror)
+            .lift_floor()
+            .for_tile(Tile::Mirror(MirrorSlant::Backslash)),
+        LevelSpawner::new(spawn_mirror)
+            .lift_floor()
+            .for_tile(Tile::Splitter(MirrorSlant::Slash)),
+        LevelSpawner::new(spawn_mirror)
+            .lift_floor()
+            .for_tile(Tile::Splitter(MirrorSlant::Backslash)),
+        LevelSpawner::new(|commands, info| {
+            commands.spawn((
+                level_tag.clone(),
+                Mesh3d(common.mesh_cube.clone()),
+                MeshMaterial3d(common.material_orange.clone()),
+                ColliderDensity(0.2),
+                Transform::from_translation(info.pos + Vec3::Y)
+                    .with_rotation(MirrorSlant::Slash.rotation())
+                    .with_scale(Vec3::splat(0.8)),
+                RigidBody::Dynamic,
+                Collider::cuboid(1.0, 1.0, 1.0),
+                // Stay upright, so that the mirror keeps turning beams along the floor. The laser
+                // plugin snaps its yaw back to a diagonal.
+                LockedAxes::new().lock_rotation_x().lock_rotation_z(),
+                Draggable::default(),
+                DespawnFalling,
+                Mirror { splits: false },
+                children![(
+                    Mesh3d(common.mesh_cube.clone()),
+                    MeshMaterial3d(common.material_mirror.clone()),
+                    Transform::from_scale(Vec3::new(0.1, 1.05, 1.05)),
+                )],
+            ));
+        })
+        .lift_floor()
+        .for_tile(Tile::MirrorCrate),
     ]
     .into_iter()
     .collect();
 
-    for (grid_position, grid_tile) in tile_grid.iter() {
+    for (grid_position, grid_tile) in parsed.grid.tiles.iter() {
         let Some(candidate) = color_spawners.get_mut(grid_tile) else {
-            eprintln!("No spawner for tile {:?}", grid_tile);
+            error!("no spawner for tile {grid_tile:?} in {}", level_tag.level);
             continue;
         };
 
@@ -1222,7 +1784,7 @@ fn load_level(
         if candidate.lift_entity || candidate.lift_floor {
             for dx in -1..=1 {
                 for dz in -1..=1 {
-                    if is_raised(info.grid + IVec2::new(dx, dz)) {
+                    if parsed.is_raised(info.grid + IVec2::new(dx, dz)) {
                         entity_height = 1.0;
                         if dx == 0 || dz == 0 {
                             floor_height = 1.0;
@@ -1236,7 +1798,7 @@ fn load_level(
             if candidate.lift_floor {
                 info.pos += Vec3::Y * floor_height;
             }
-            spawn_floor(commands, info.pos);
+            add_block(info.pos, common.material_gray.clone());
             if candidate.lift_floor {
                 info.pos -= Vec3::Y * floor_height;
             }
@@ -1252,15 +1814,15 @@ fn load_level(
 
     std::mem::drop(color_spawners);
 
+    spawn_blocks(&level_tag, commands, common, shift, &blocks.lock().unwrap());
+
     // Spawn ziplines
-    spawn_ziplines(
-        &level_tag,
-        commands,
-        common,
-        &zipline_positions.lock().unwrap(),
-    );
+    let zipline_positions = zipline_positions.lock().unwrap();
+    for zipline in parsed.ziplines.iter() {
+        spawn_zipline(&level_tag, commands, common, zipline, &zipline_positions);
+    }
 
-    for hallway_pattern in get_hallway_junctions(image) {
+    for hallway_pattern in parsed.hallways.iter() {
         for p in hallway_pattern.grids.iter() {
             commands.spawn((
                 level_tag.clone(),
@@ -1269,7 +1831,7 @@ fn load_level(
                 Transform::from_translation(shift + Vec3::new(p.x as f32, 1.0, p.y as f32))
                     .with_scale(Vec3::splat(0.7)),
                 Hallway {
-                    pattern: hallway_pattern.pattern,
+                    pattern: hallway_pattern.pattern.clone(),
                     rooms: junction_to_levels[&hallway_pattern.pattern].clone(),
                 },
                 SpawnPoint {}, // Hallways are also spawn points
@@ -1277,67 +1839,95 @@ fn load_level(
         }
     }
 
-    spawn_chains(&level_tag, commands, common, &chains.lock().unwrap());
-    println!("spawn chains done");
+    // Chain links are joined to each other by entity, so each chain spawns all at once.
+    let chains = chains.lock().unwrap();
+    for group in parsed.chains.iter() {
+        let level_tag = level_tag.clone();
+        let common = common.clone();
+        let group = group.clone();
+        let chains = chains.clone();
+        // A length set on any of the chain's tiles lets the chain snap.
+        let snap_length = group.links.iter().find_map(|link| {
+            level
+                .tile_settings
+                .get(link)
+                .and_then(|settings| settings.length)
+        });
+        commands.queue(group.links.len() * 2, move |commands| {
+            spawn_chain(&level_tag, commands, &common, &group, &chains, snap_length);
+        });
+    }
 }
 
-fn spawn_ziplines(
+/// Spawns the floor and wall cubes of a level, merging each rectangle of cubes with the same
+/// height and material into a single entity.
+fn spawn_blocks(
     level_tag: &LevelTag,
-    commands: &mut Commands,
+    commands: &mut LevelCommands,
     common: &Common,
-    zipline_positions: &HashMap<IVec2, Vec3>,
+    shift: Vec3,
+    blocks: &HashMap<Handle<StandardMaterial>, HashSet<IVec3>>,
 ) {
-    fn neighbors8(p: IVec2) -> Vec<IVec2> {
-        let mut out: Vec<IVec2> = Vec::with_capacity(8);
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                if (dx, dy) != (0, 0) {
-                    out.push(p + IVec2::new(dx, dy));
-                }
+    for (material, cells) in blocks.iter() {
+        let mut layers: HashMap<i32, HashSet<IVec2>> = HashMap::new();
+        for cell in cells.iter() {
+            layers.entry(cell.y).or_default().insert(cell.xz());
+        }
+
+        for (y, layer) in layers {
+            for (corner, size) in merge_rectangles(&layer) {
+                let center = corner.as_vec2() + (size - IVec2::ONE).as_vec2() / 2.;
+                commands.spawn((
+                    level_tag.clone(),
+                    Mesh3d(common.mesh_cube.clone()),
+                    MeshMaterial3d(material.clone()),
+                    Transform::from_translation(shift + Vec3::new(center.x, y as f32, center.y))
+                        .with_scale(Vec3::new(size.x as f32, 1., size.y as f32)),
+                    RigidBody::Static,
+                    Collider::cuboid(1., 1., 1.),
+                    Footprint { size },
+                ));
             }
         }
-        out
     }
+}
+
+/// Greedily covers the cells with non-overlapping rectangles, as `(corner, size)` pairs.
+fn merge_rectangles(cells: &HashSet<IVec2>) -> Vec<(IVec2, IVec2)> {
+    let mut sorted: Vec<IVec2> = cells.iter().copied().collect();
+    sorted.sort_by_key(|p| (p.y, p.x));
+
+    let mut covered: HashSet<IVec2> = HashSet::new();
+    let is_free = |covered: &HashSet<IVec2>, p: IVec2| cells.contains(&p) && !covered.contains(&p);
 
-    // Group the ziplines into contiguous loops or lines.
-    let mut visited: HashSet<IVec2> = HashSet::new();
-    for &p in zipline_positions.keys() {
-        if visited.contains(&p) {
+    let mut rectangles = Vec::new();
+    for corner in sorted {
+        if covered.contains(&corner) {
             continue;
         }
 
-        if neighbors8(p)
-            .into_iter()
-            .filter(|neighbor| zipline_positions.contains_key(neighbor))
-            .count()
-            != 1
-        {
-            // Only visit from the ends of a line, so that the region is built in-order.
-            continue;
+        let mut size = IVec2::ONE;
+        while is_free(&covered, corner + IVec2::X * size.x) {
+            size.x += 1;
+        }
+        while (0..size.x).all(|dx| is_free(&covered, corner + IVec2::new(dx, size.y))) {
+            size.y += 1;
         }
 
-        visited.insert(p);
-        let mut region = vec![p];
-        let mut i = 0;
-        while i < region.len() {
-            let q = region[i];
-            for neighbor in neighbors8(q) {
-                if !visited.contains(&neighbor) && zipline_positions.contains_key(&neighbor) {
-                    visited.insert(neighbor);
-                    region.push(neighbor);
-                }
+        for dx in 0..size.x {
+            for dy in 0..size.y {
+                covered.insert(corner + IVec2::new(dx, dy));
             }
-            i += 1;
         }
-
-        spawn_zipline(level_tag, commands, common, &region, zipline_positions);
+        rectangles.push((corner, size));
     }
+    rectangles
 }
 
 /// Spawn a single zipline, in order.
 fn spawn_zipline(
     level_tag: &LevelTag,
-    commands: &mut Commands,
+    commands: &mut LevelCommands,
     common: &Common,
     zipline_positions: &[IVec2],
     world_positions: &HashMap<IVec2, Vec3>,
@@ -1373,66 +1963,76 @@ fn spawn_zipline(
     ));
 }
 
-//Flood fill spawn one chain, chain_ball input is the starting position.
-fn spawn_chain(
-    chain_positions: &HashMap<IVec2, Vec3>,
-    starting_chain_ball: (IVec2, Vec3),
-    visited: &mut HashMap<IVec2, bool>,
-) -> Vec<(IVec2, Vec3)> {
-    //valid chain dirs
-    let dirs = [ivec2(0, 1), ivec2(0, -1), ivec2(1, 0), ivec2(-1, 0)];
-    let mut chain_list: Vec<(IVec2, Vec3)> = Vec::new();
-    let mut chain_q = VecDeque::new();
-    chain_q.push_back(starting_chain_ball);
-
-    //bfs
-    while let Some(curr_chain) = chain_q.pop_front() {
-        let curr_chain_ball = curr_chain.0;
-        chain_list.push(curr_chain);
-        for d in dirs {
-            let next_chain_key = ivec2(curr_chain_ball.x + d[0], curr_chain_ball.y + d[1]); //potential same chain
-            if chain_positions.contains_key(&next_chain_key)
-                && !visited.contains_key(&next_chain_key)
-            {
-                //validate
-                chain_q.push_back((next_chain_key, chain_positions[&next_chain_key]));
-            };
-        }
-        visited.insert(curr_chain_ball, true);
-    }
-
-    chain_list
+/// Spawns a cable reel: a drum and a plug on a cord that reaches `length` from it. Both ends are
+/// plugs of the same cable.
+fn spawn_cable_reel(
+    level_tag: &LevelTag,
+    commands: &mut Commands,
+    common: &Common,
+    pos: Vec3,
+    length: f32,
+) {
+    let drum = commands
+        .spawn((
+            level_tag.clone(),
+            Mesh3d(common.mesh_cylinder.clone()),
+            MeshMaterial3d(common.material_orange.clone()),
+            Transform::from_translation(pos + Vec3::Y).with_scale(Vec3::new(0.8, 0.5, 0.8)),
+            RigidBody::Dynamic,
+            ColliderDensity(0.3),
+            Collider::cylinder(0.5, 1.0),
+            Draggable::default(),
+            DespawnFalling,
+        ))
+        .id();
+    commands.entity(drum).insert(Plug::new(drum));
+    let plug = commands
+        .spawn((
+            level_tag.clone(),
+            Mesh3d(common.mesh_cube.clone()),
+            MeshMaterial3d(common.material_orange.clone()),
+            Transform::from_translation(pos + Vec3::Y * 1.6).with_scale(Vec3::splat(0.6)),
+            RigidBody::Dynamic,
+            ColliderDensity(0.1),
+            Collider::cuboid(1., 1., 1.),
+            Draggable::default(),
+            DespawnFalling,
+            Plug::new(drum),
+        ))
+        .id();
+    commands.spawn((
+        level_tag.clone(),
+        DistanceJoint::new(drum, plug)
+            .with_limits(0.0, length)
+            .with_compliance(0.02),
+        ChainLink(drum, plug),
+        LinkLength(length),
+        Mesh3d(common.mesh_cube.clone()),
+        MeshMaterial3d(common.material_dark_gray.clone()),
+    ));
 }
 
-fn spawn_chains(
+/// Spawn a single chain, with a plug at each end.
+fn spawn_chain(
     level_tag: &LevelTag,
     commands: &mut Commands,
     common: &Common,
-    chain_positions: &HashMap<IVec2, Vec3>,
+    group: &ChainGroup,
+    world_positions: &HashMap<IVec2, Vec3>,
+    snap_length: Option<f32>,
 ) {
-    let mut visited: HashMap<IVec2, bool> = HashMap::new();
-    let mut chain_locs: Vec<Vec<(IVec2, Vec3)>> = Vec::new();
-
-    for (chain_ball, chain_pos) in chain_positions.iter() {
-        //call spawn chain when encountering and unvisited chainball
-        if !visited.contains_key(chain_ball) {
-            let chain_loc = spawn_chain(chain_positions, (*chain_ball, *chain_pos), &mut visited);
-            chain_locs.push(chain_loc);
-        }
-    }
-
-    if chain_locs.len() >= 2 {
-        for chain in chain_locs {
-            spawn_chains(level_tag, commands, common, &chain.into_iter().collect());
-        }
-        return;
-    }
+    let chain_positions: HashMap<IVec2, Vec3> = group
+        .links
+        .iter()
+        .map(|link| (*link, world_positions[link]))
+        .collect();
 
     let mut chain_entities: HashMap<IVec2, Entity> = default();
 
     let mut chain_ends: Vec<(Entity, Vec3)> = Vec::new();
 
-    for (&chain_ball, &chain_pos) in chain_positions {
+    for &chain_ball in group.links.iter() {
+        let chain_pos = chain_positions[&chain_ball];
         let collision_layer = if (chain_ball.x + chain_ball.y) % 2 == 0 {
             let mut interact = LayerMask::ALL;
             interact.remove(4);
@@ -1454,31 +2054,32 @@ fn spawn_chains(
                 collision_layer,
             ))
             .id();
-        if [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
-            .into_iter()
-            .filter(|d| chain_positions.contains_key(&(chain_ball + *d)))
-            .count()
-            == 1
-        {
-            println!("These are chain ends {:?}", (chain_id, chain_pos));
+        if group.ends.contains(&chain_ball) {
             chain_ends.push((chain_id, chain_pos));
         }
         chain_entities.insert(chain_ball, chain_id);
     }
 
-    for (end_index, (chain_end, chain_pos)) in chain_ends.iter().enumerate() {
-        let other_end = chain_ends[1 - end_index].0;
+    // Chains with more than two ends are splitters, with a plug on every end.
+    if chain_ends.len() < 2 {
+        error!(
+            "chain at {} in {} has {} ends, so it has no plugs",
+            group.links[0],
+            level_tag.level,
+            chain_ends.len()
+        );
+        chain_ends.clear();
+    }
 
+    let cable = chain_ends.first().map(|(chain_end, _)| *chain_end);
+    for (chain_end, chain_pos) in chain_ends.iter() {
         commands.entity(*chain_end).insert((
             Draggable::default(),
             Collider::cuboid(1., 1., 1.),
             Mesh3d(common.mesh_cube.clone()),
             Transform::from_translation(*chain_pos).with_scale(Vec3::splat(0.6)),
             MeshMaterial3d(common.material_orange.clone()),
-            Plug {
-                outlet: None,
-                other_end,
-            },
+            Plug::new(cable.unwrap()),
         ));
     }
 
@@ -1498,7 +2099,7 @@ fn spawn_chains(
                 // Add a constraint between them.
                 let delta = chain_positions[&other] - chain_positions[chain_ball];
 
-                commands.spawn((
+                let mut link = commands.spawn((
                     level_tag.clone(),
                     SphericalJoint::new(chain_entities[chain_ball], chain_entities[&other])
                         .with_local_anchor_1(delta / 2.0)
@@ -1507,9 +2108,13 @@ fn spawn_chains(
                         .with_angular_velocity_damping(0.5)
                         .with_compliance(0.02),
                     ChainLink(chain_entities[chain_ball], chain_entities[&other]),
+                    LinkLength(delta.length()),
                     Mesh3d(common.mesh_cube.clone()),
                     MeshMaterial3d(common.material_dark_gray.clone()),
                 ));
+                if let Some(max_length) = snap_length {
+                    link.insert(ChainSnap { max_length });
+                }
             }
         }
     }
@@ -1519,11 +2124,13 @@ const WIRE_WIDTH: f32 = 0.3;
 const WIRE_EXTENT: f32 = 0.7;
 
 fn spawn_floor_wire_segment(
-    commands: &mut Commands,
+    commands: &mut LevelCommands,
     common: &Common,
     level_tag: &LevelTag,
     pos: Vec3,
     dir: IVec2,
+    channel: Channel,
+    layer: GridLayer,
 ) {
     let center = pos + Vec3::Y * 0.5;
     let shift = Vec3::new(dir.x as f32, 0., dir.y as f32) * WIRE_EXTENT / 2.;
@@ -1538,24 +2145,38 @@ fn spawn_floor_wire_segment(
     commands.spawn((
         level_tag.clone(),
         Mesh3d(common.mesh_cube.clone()),
-        MeshMaterial3d(common.material_electricity.clone()),
+        MeshMaterial3d(common.material_dark_blue.clone()),
         Transform::from_translation(center + shift).with_scale(scale),
         Wire,
+        channel,
+        layer,
     ));
 }
 
+/// Spawns the wire segments from the tile at `grid` towards each of its connections.
 fn spawn_floor_wire(
-    commands: &mut Commands,
+    commands: &mut LevelCommands,
     common: &Common,
-    tile_grid: &HashMap<IVec2, Tile>,
-    level_tag: &LevelTag,
+    parsed: &ParsedLevel,
     grid: IVec2,
+    level_tag: &LevelTag,
     pos: Vec3,
 ) {
-    for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
-        let neighbor = &tile_grid[&(grid + IVec2::new(dx, dz))];
-        if is_electrical(neighbor) {
-            spawn_floor_wire_segment(commands, common, level_tag, pos, IVec2::new(dx, dz));
-        }
+    for &dir in parsed.wires[&grid].iter() {
+        // Each arm of a crossing is on the channel of the wire it leads to.
+        let channel = if parsed.tile(grid) == Tile::ChannelCrossing {
+            parsed.channel(grid + dir)
+        } else {
+            parsed.channel(grid)
+        };
+        spawn_floor_wire_segment(
+            commands,
+            common,
+            level_tag,
+            pos,
+            dir,
+            channel,
+            parsed.layer(grid),
+        );
     }
 }
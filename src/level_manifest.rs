@@ -0,0 +1,254 @@
+use bevy::{platform::collections::HashSet, prelude::*};
+use serde::Deserialize;
+
+/// The list of levels in the game, loaded from `manifest.levels.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct LevelManifest {
+    /// The file name of the level that the player starts in.
+    pub starting_level: String,
+    pub levels: Vec<LevelEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LevelEntry {
+    /// The file name of the level's map image, relative to `assets/`.
+    pub file: String,
+    /// A human-readable name for the level.
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// The music cue to play while the player is in this level.
+    #[serde(default)]
+    pub music: Option<String>,
+    /// Signs, tutorials and decals placed in the level.
+    #[serde(default)]
+    pub signs: Vec<SignEntry>,
+    /// Settings for individual tiles of the level's map.
+    #[serde(default)]
+    pub tile_settings: Vec<TileSettings>,
+}
+
+/// Tunes a single tile of a level's map. Each field only applies to some kinds of tile.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TileSettings {
+    /// The tile's pixel position in the map image.
+    pub position: (i32, i32),
+    /// For pressure plates, the total mass needed to press the plate.
+    #[serde(default)]
+    pub min_mass: Option<f32>,
+    /// For timed buttons, how many seconds the button stays on once pressed.
+    #[serde(default)]
+    pub duration: Option<f32>,
+    /// For capacitors and batteries, how many seconds they can power their net for.
+    #[serde(default)]
+    pub capacity: Option<f32>,
+    /// For wireless transmitters and receivers, the name that pairs them, even across levels.
+    #[serde(default)]
+    pub link: Option<String>,
+    /// For cable reels, how far the cord reaches from the drum. For chains, how far apart two
+    /// neighboring links can be pulled before the chain snaps there. Chains without one never
+    /// snap.
+    #[serde(default)]
+    pub length: Option<f32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SignEntry {
+    /// The image shown on the sign, relative to `assets/`.
+    pub image: String,
+    /// The position of the sign, relative to the level's origin.
+    pub position: (f32, f32, f32),
+    /// The direction the sign's face points. By default, signs lie tilted back towards the camera.
+    #[serde(default = "default_sign_facing")]
+    pub facing: (f32, f32, f32),
+    /// The direction of the top of the sign's image.
+    #[serde(default = "default_sign_up")]
+    pub up: (f32, f32, f32),
+    /// The width and height of the sign.
+    #[serde(default = "default_sign_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub appear: SignAppear,
+}
+
+fn default_sign_facing() -> (f32, f32, f32) {
+    (0.0, 1.0, 0.9)
+}
+
+fn default_sign_up() -> (f32, f32, f32) {
+    (0.0, -1.0, 0.0)
+}
+
+fn default_sign_scale() -> f32 {
+    6.0
+}
+
+/// When a sign becomes visible.
+#[derive(Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum SignAppear {
+    #[default]
+    Always,
+    /// Once the player has collected the ruby, and can use ziplines.
+    RubyCollected,
+}
+
+impl LevelManifest {
+    /// Checks that the manifest describes a playable set of levels.
+    pub fn validate(&self) -> Result<(), LevelManifestError> {
+        if self.levels.is_empty() {
+            return Err(LevelManifestError::NoLevels);
+        }
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        for (index, level) in self.levels.iter().enumerate() {
+            if level.file.is_empty() {
+                return Err(LevelManifestError::MissingFile { index });
+            }
+            if !seen.insert(&level.file) {
+                return Err(LevelManifestError::DuplicateLevel(level.file.clone()));
+            }
+            for sign in level.signs.iter() {
+                if sign.image.is_empty() {
+                    return Err(LevelManifestError::MissingSignImage(level.file.clone()));
+                }
+                let facing = Vec3::from(sign.facing);
+                if facing == Vec3::ZERO || Vec3::from(sign.up).cross(facing) == Vec3::ZERO {
+                    return Err(LevelManifestError::InvalidSignOrientation(
+                        level.file.clone(),
+                    ));
+                }
+                if sign.scale <= 0.0 {
+                    return Err(LevelManifestError::InvalidSignScale(level.file.clone()));
+                }
+            }
+            let mut positions: HashSet<(i32, i32)> = HashSet::new();
+            for settings in level.tile_settings.iter() {
+                if !positions.insert(settings.position) {
+                    return Err(LevelManifestError::DuplicateTileSettings(
+                        level.file.clone(),
+                        settings.position,
+                    ));
+                }
+                if settings.min_mass.is_some_and(|mass| mass < 0.0) {
+                    return Err(LevelManifestError::InvalidMinMass(
+                        level.file.clone(),
+                        settings.position,
+                    ));
+                }
+                if settings
+                    .duration
+                    .is_some_and(|duration| !duration.is_finite() || duration <= 0.0)
+                {
+                    return Err(LevelManifestError::InvalidDuration(
+                        level.file.clone(),
+                        settings.position,
+                    ));
+                }
+                if settings
+                    .capacity
+                    .is_some_and(|capacity| !capacity.is_finite() || capacity <= 0.0)
+                {
+                    return Err(LevelManifestError::InvalidCapacity(
+                        level.file.clone(),
+                        settings.position,
+                    ));
+                }
+                if settings.link.as_ref().is_some_and(|link| link.is_empty()) {
+                    return Err(LevelManifestError::EmptyLink(
+                        level.file.clone(),
+                        settings.position,
+                    ));
+                }
+                if settings.length.is_some_and(|length| length <= 0.0) {
+                    return Err(LevelManifestError::InvalidLength(
+                        level.file.clone(),
+                        settings.position,
+                    ));
+                }
+            }
+        }
+
+        if !seen.contains(self.starting_level.as_str()) {
+            return Err(LevelManifestError::UnknownStartingLevel(
+                self.starting_level.clone(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A problem with the contents of a [`LevelManifest`].
+#[derive(Debug)]
+pub enum LevelManifestError {
+    NoLevels,
+    MissingFile { index: usize },
+    DuplicateLevel(String),
+    MissingSignImage(String),
+    InvalidSignOrientation(String),
+    InvalidSignScale(String),
+    DuplicateTileSettings(String, (i32, i32)),
+    InvalidMinMass(String, (i32, i32)),
+    InvalidDuration(String, (i32, i32)),
+    InvalidCapacity(String, (i32, i32)),
+    EmptyLink(String, (i32, i32)),
+    InvalidLength(String, (i32, i32)),
+    UnknownStartingLevel(String),
+}
+
+impl std::fmt::Display for LevelManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoLevels => write!(f, "the manifest does not list any levels"),
+            Self::MissingFile { index } => write!(f, "level #{index} has an empty `file`"),
+            Self::DuplicateLevel(file) => write!(f, "level {file} is listed more than once"),
+            Self::MissingSignImage(file) => {
+                write!(f, "level {file} has a sign with an empty `image`")
+            }
+            Self::InvalidSignOrientation(file) => write!(
+                f,
+                "level {file} has a sign whose `facing` is zero or parallel to its `up`"
+            ),
+            Self::InvalidSignScale(file) => {
+                write!(
+                    f,
+                    "level {file} has a sign with a `scale` that is not positive"
+                )
+            }
+            Self::DuplicateTileSettings(file, (x, z)) => {
+                write!(
+                    f,
+                    "level {file} has more than one tile setting for ({x}, {z})"
+                )
+            }
+            Self::InvalidMinMass(file, (x, z)) => {
+                write!(f, "level {file} has a negative `min_mass` at ({x}, {z})")
+            }
+            Self::InvalidDuration(file, (x, z)) => {
+                write!(
+                    f,
+                    "level {file} has a `duration` that is not a positive number at ({x}, {z})"
+                )
+            }
+            Self::InvalidCapacity(file, (x, z)) => {
+                write!(
+                    f,
+                    "level {file} has a `capacity` that is not a positive number at ({x}, {z})"
+                )
+            }
+            Self::EmptyLink(file, (x, z)) => {
+                write!(f, "level {file} has an empty `link` at ({x}, {z})")
+            }
+            Self::InvalidLength(file, (x, z)) => {
+                write!(
+                    f,
+                    "level {file} has a `length` that is not positive at ({x}, {z})"
+                )
+            }
+            Self::UnknownStartingLevel(file) => {
+                write!(f, "the starting level {file} is not listed in `levels`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelManifestError {}
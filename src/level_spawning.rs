@@ -0,0 +1,98 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::level::LevelName;
+
+/// How many level entities may be spawned each frame.
+#[derive(Resource)]
+pub struct LevelSpawnBudget {
+    pub entities_per_frame: usize,
+}
+
+impl Default for LevelSpawnBudget {
+    fn default() -> Self {
+        Self {
+            entities_per_frame: 200,
+        }
+    }
+}
+
+struct SpawnJob {
+    level: LevelName,
+    /// Roughly how many entities the job spawns, counted against the budget.
+    entities: usize,
+    spawn: Box<dyn FnOnce(&mut Commands) + Send + Sync>,
+}
+
+/// Level entities waiting to be spawned, in order.
+#[derive(Resource, Default)]
+pub struct SpawnQueue {
+    jobs: VecDeque<SpawnJob>,
+}
+
+impl SpawnQueue {
+    /// Returns a [`Commands`]-like handle that queues spawns for the level.
+    pub fn for_level(&mut self, level: &LevelName) -> LevelCommands<'_> {
+        LevelCommands {
+            level: level.clone(),
+            jobs: &mut self.jobs,
+        }
+    }
+
+    /// Whether any of the level's entities are still waiting to spawn.
+    pub fn is_loading(&self, level: &LevelName) -> bool {
+        self.jobs.iter().any(|job| job.level == *level)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Drops everything still waiting to spawn for the level.
+    pub fn cancel(&mut self, level: &LevelName) {
+        self.jobs.retain(|job| job.level != *level);
+    }
+}
+
+pub struct LevelCommands<'a> {
+    level: LevelName,
+    jobs: &'a mut VecDeque<SpawnJob>,
+}
+
+impl LevelCommands<'_> {
+    pub fn spawn(&mut self, bundle: impl Bundle) {
+        self.queue(1, move |commands| {
+            commands.spawn(bundle);
+        });
+    }
+
+    /// Queues a job that spawns about `entities` entities at once, such as an entity and its
+    /// children, or entities that need each other's ids.
+    pub fn queue(
+        &mut self,
+        entities: usize,
+        spawn: impl FnOnce(&mut Commands) + Send + Sync + 'static,
+    ) {
+        self.jobs.push_back(SpawnJob {
+            level: self.level.clone(),
+            entities,
+            spawn: Box::new(spawn),
+        });
+    }
+}
+
+pub(crate) fn spawn_queued_system(
+    mut commands: Commands,
+    mut queue: ResMut<SpawnQueue>,
+    budget: Res<LevelSpawnBudget>,
+) {
+    let mut spent = 0;
+    while spent < budget.entities_per_frame {
+        let Some(job) = queue.jobs.pop_front() else {
+            break;
+        };
+        spent += job.entities.max(1);
+        (job.spawn)(&mut commands);
+    }
+}
@@ -0,0 +1,31 @@
+pub mod active_levels;
+pub mod capacitor;
+pub mod chain;
+pub mod common;
+pub mod door;
+pub mod draggable;
+pub mod electricity;
+pub mod email_spawner;
+pub mod evil_robot;
+pub mod fog;
+pub mod interactible;
+pub mod intro;
+pub mod laser;
+pub mod level;
+pub mod level_manifest;
+pub mod level_spawning;
+pub mod mainframe;
+pub mod palette;
+pub mod parsed_level;
+pub mod player;
+#[cfg(feature = "dev")]
+pub mod power_debug;
+pub mod power_network;
+pub mod pressure_plate;
+pub mod ron_asset;
+pub mod ruby;
+pub mod spawn_point;
+pub mod switch;
+pub mod well;
+pub mod wireless;
+pub mod zipline;
@@ -1,22 +1,3 @@
-pub mod chain;
-pub mod common;
-pub mod door;
-pub mod draggable;
-pub mod electricity;
-pub mod email_spawner;
-pub mod evil_robot;
-pub mod fog;
-pub mod interactible;
-pub mod intro;
-pub mod laser;
-pub mod level;
-pub mod mainframe;
-pub mod player;
-pub mod ruby;
-pub mod spawn_point;
-pub mod well;
-pub mod zipline;
-
 use avian3d::prelude::*;
 
 use bevy::{
@@ -27,50 +8,67 @@ use bevy::{
     prelude::*,
     render::view::{ColorGrading, ColorGradingGlobal},
 };
-use common::{CommonPlugin, setup_common};
-use door::DoorPlugin;
-use draggable::DraggablePlugin;
-use evil_robot::EvilRobotPlugin;
-use interactible::InteractiblePlugin;
-use mainframe::MainframePlugin;
-use player::{PlayerCamera, PlayerPlugin};
-use spawn_point::SpawnPointPlugin;
-use well::WellPlugin;
-
-use crate::{
-    chain::ChainPlugin, electricity::ElectricityPlugin, email_spawner::EmailSpawnerPlugin,
-    fog::FogPlugin, intro::IntroPlugin, laser::LaserPlugin, level::LevelPlugin, ruby::RubyPlugin,
+use bevy_github_ci_template::{
+    capacitor::CapacitorPlugin,
+    chain::ChainPlugin,
+    common::{CommonPlugin, setup_common},
+    door::DoorPlugin,
+    draggable::DraggablePlugin,
+    electricity::ElectricityPlugin,
+    email_spawner::EmailSpawnerPlugin,
+    evil_robot::EvilRobotPlugin,
+    fog::FogPlugin,
+    interactible::InteractiblePlugin,
+    intro::IntroPlugin,
+    laser::LaserPlugin,
+    level::LevelPlugin,
+    mainframe::MainframePlugin,
+    player::{PlayerCamera, PlayerPlugin},
+    pressure_plate::PressurePlatePlugin,
+    ruby::RubyPlugin,
+    spawn_point::SpawnPointPlugin,
+    switch::SwitchPlugin,
+    well::WellPlugin,
+    wireless::WirelessPlugin,
     zipline::ZiplinePlugin,
 };
 
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins.set(ImagePlugin::default_nearest()),
-            PhysicsPlugins::default(),
-            PlayerPlugin,
-            InteractiblePlugin,
-            MainframePlugin,
-            LevelPlugin,
-            DraggablePlugin,
-            EvilRobotPlugin,
-            ZiplinePlugin,
-            SpawnPointPlugin,
-            CommonPlugin,
-            LaserPlugin,
-            WellPlugin,
-            DoorPlugin,
-            FogPlugin,
-        ))
-        .add_plugins((
-            IntroPlugin,
-            ChainPlugin,
-            RubyPlugin,
-            EmailSpawnerPlugin,
-            ElectricityPlugin,
-        ))
-        .add_systems(Startup, setup.after(setup_common))
-        .run();
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins.set(ImagePlugin::default_nearest()),
+        PhysicsPlugins::default(),
+        PlayerPlugin,
+        InteractiblePlugin,
+        MainframePlugin,
+        LevelPlugin,
+        DraggablePlugin,
+        EvilRobotPlugin,
+        ZiplinePlugin,
+        SpawnPointPlugin,
+        CommonPlugin,
+        LaserPlugin,
+        WellPlugin,
+        DoorPlugin,
+        FogPlugin,
+    ))
+    .add_plugins((
+        IntroPlugin,
+        ChainPlugin,
+        RubyPlugin,
+        EmailSpawnerPlugin,
+        ElectricityPlugin,
+        PressurePlatePlugin,
+        SwitchPlugin,
+        CapacitorPlugin,
+        WirelessPlugin,
+    ))
+    .add_systems(Startup, setup.after(setup_common));
+
+    #[cfg(feature = "dev")]
+    app.add_plugins(bevy_github_ci_template::power_debug::PowerDebugPlugin);
+
+    app.run();
 }
 
 fn setup(mut commands: Commands) {
@@ -2,9 +2,11 @@ use bevy::{platform::collections::HashSet, prelude::*};
 
 use crate::{
     common::Common,
+    electricity::PowerPart,
     email_spawner::EmailSpawner,
     interactible::{Activated, Interactible},
     level::{LevelName, LevelTag},
+    power_network::PowerRole,
 };
 
 fn mainframe_point_light() -> PointLight {
@@ -26,6 +28,7 @@ pub struct RememberedMainframes {
 #[require(
     Interactible = Interactible::radius(1.9).with_priority(2).with_dot_offset(Vec3::Y * 1.2),
     PointLight = mainframe_point_light(),
+    PowerPart = PowerPart::new(PowerRole::Load),
 )]
 pub struct Mainframe {
     /// Whether the player has activate the mainframe.
@@ -51,6 +54,24 @@ impl Plugin for MainframePlugin {
             )
                 .chain(),
         );
+        app.add_systems(
+            FixedUpdate,
+            mainframe_power_role_system.before(crate::electricity::compute_charge_system),
+        );
+    }
+}
+
+/// Active mainframes power their neighbors, while inactive ones only draw power.
+fn mainframe_power_role_system(mut mainframes: Query<(&Mainframe, &mut PowerPart)>) {
+    for (mainframe, mut part) in mainframes.iter_mut() {
+        let role = if mainframe.active {
+            PowerRole::Source
+        } else {
+            PowerRole::Load
+        };
+        if part.role != role {
+            part.role = role;
+        }
     }
 }
 
@@ -0,0 +1,231 @@
+use bevy::{
+    color::color_difference::EuclideanDistance, platform::collections::HashMap, prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{
+    electricity::{Channel, DiodeDirection},
+    laser::MirrorSlant,
+    power_network::GateKind,
+    pressure_plate::PlateMode,
+    switch::SwitchKind,
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize)]
+pub enum Tile {
+    Floor,
+    ElevatedFloor,
+    Ramp,
+    Hallway(i32),
+    Wall,
+    ComputerMainframe,
+    ComputerMainframeBig,
+    Outside,
+    Zappy,
+    Well,
+    Door,
+    Crate,
+    LaserSource,
+    PlayerStart,
+    SpawnPoint,
+    Zipline,
+    ZiplineOverWell,
+    Chain,
+    Outlet,
+    PowerSource,
+    FloorWire,
+    CrossFloorWire,
+    WallWire,
+    Ruby,
+    Gate(GateKind),
+    /// The wire a logic gate drives. Every other electrical side of a gate is an input.
+    GateOutput,
+    PressurePlate(PlateMode),
+    Switch(SwitchKind),
+    /// A wire that only passes charge in one direction.
+    Diode(DiodeDirection),
+    /// A floor wire on its own channel, which doesn't connect to wires of other channels.
+    ChannelWire(Channel),
+    /// Carries each channel straight across, so that two circuits can cross without connecting.
+    ChannelCrossing,
+    Capacitor,
+    /// A charged battery, which the player can carry between outlets.
+    Battery,
+    /// A wire running straight up, which joins the wires on the ground to the wires on the
+    /// elevated floors beside it.
+    VerticalWire,
+    /// Sends the power of its net to every receiver with the same link.
+    Transmitter,
+    /// Powers its net while its transmitter is powered.
+    Receiver,
+    /// A drum with a plug on a cord, which the player can carry as a long extension cable.
+    CableReel,
+    /// A laser source that only fires while its net is powered.
+    PoweredLaserSource,
+    /// Powers its net while a laser beam hits it.
+    LaserReceptor,
+    /// A fixed mirror, which turns laser beams by 90 degrees.
+    Mirror(MirrorSlant),
+    /// A fixed half-silvered mirror, which turns laser beams by 90 degrees and also lets them
+    /// through.
+    Splitter(MirrorSlant),
+    /// A crate with a mirror through its middle, which the player can carry.
+    MirrorCrate,
+}
+
+/// Maps the pixel colors of level images to tiles, loaded from `tiles.palette.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct Palette {
+    /// The tolerance used by entries that don't specify their own.
+    pub default_tolerance: f32,
+    pub tiles: Vec<PaletteEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PaletteEntry {
+    pub tile: Tile,
+    /// A human-readable name for the tile, used in error messages.
+    pub name: String,
+    /// The linear RGB color of the tile's pixels, from 0 to 255.
+    pub color: (u8, u8, u8),
+    /// How far (in linear RGB) a pixel may be from `color` and still count as this tile.
+    #[serde(default)]
+    pub tolerance: Option<f32>,
+}
+
+impl PaletteEntry {
+    pub fn color(&self) -> Color {
+        let (r, g, b) = self.color;
+        Color::linear_rgb(r as f32 / 255., g as f32 / 255., b as f32 / 255.)
+    }
+}
+
+impl Palette {
+    fn tolerance(&self, entry: &PaletteEntry) -> f32 {
+        entry.tolerance.unwrap_or(self.default_tolerance)
+    }
+
+    /// Returns the palette entry closest to the color, if it is within that entry's tolerance.
+    pub fn entry_for(&self, color: &Color) -> Option<&PaletteEntry> {
+        let color_distance_scale = 10_000;
+
+        let (color_distance, candidate) = self
+            .tiles
+            .iter()
+            .map(|candidate| (candidate.color().distance(color), candidate))
+            .min_by_key(|a| (a.0 * color_distance_scale as f32) as i64)?;
+
+        if color_distance > self.tolerance(candidate) {
+            return None;
+        }
+
+        Some(candidate)
+    }
+
+    /// Converts from the color of the pixel to the type of tile.
+    pub fn tile_for(&self, color: &Color) -> Option<Tile> {
+        self.entry_for(color).map(|entry| entry.tile)
+    }
+
+    /// Checks that no two tiles are close enough to be mistaken for each other.
+    pub fn validate(&self) -> Result<(), PaletteError> {
+        for (i, a) in self.tiles.iter().enumerate() {
+            for b in self.tiles[i + 1..].iter() {
+                let distance = a.color().distance(&b.color());
+                if distance <= self.tolerance(a).max(self.tolerance(b)) {
+                    return Err(PaletteError::Collision {
+                        first: a.name.clone(),
+                        second: b.name.clone(),
+                        distance,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A problem with the contents of a [`Palette`].
+#[derive(Debug)]
+pub enum PaletteError {
+    Collision {
+        first: String,
+        second: String,
+        distance: f32,
+    },
+}
+
+impl std::fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Collision {
+                first,
+                second,
+                distance,
+            } => write!(
+                f,
+                "tiles {first} and {second} are only {distance:.3} apart, which is within their tolerance"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+/// The tiles of a level image, decoded with a [`Palette`].
+pub struct TileGrid {
+    pub size: IVec2,
+    pub tiles: HashMap<IVec2, Tile>,
+    /// Pixels whose color is not in the palette. These are decoded as [`Tile::Outside`].
+    pub unknown: Vec<(IVec2, Color)>,
+}
+
+impl TileGrid {
+    pub fn decode(image: &Image, palette: &Palette) -> Self {
+        Self::decode_with(image.size(), palette, |x, z| {
+            image
+                .get_color_at(x, z)
+                .expect("must be able to get color in image")
+        })
+    }
+
+    /// Decodes a tightly-packed, linear RGBA8 buffer.
+    pub fn decode_rgba(size: UVec2, rgba: &[u8], palette: &Palette) -> Self {
+        Self::decode_with(size, palette, |x, z| {
+            let i = 4 * (z * size.x + x) as usize;
+            Color::linear_rgba(
+                rgba[i] as f32 / 255.,
+                rgba[i + 1] as f32 / 255.,
+                rgba[i + 2] as f32 / 255.,
+                rgba[i + 3] as f32 / 255.,
+            )
+        })
+    }
+
+    fn decode_with(size: UVec2, palette: &Palette, color_at: impl Fn(u32, u32) -> Color) -> Self {
+        let mut tiles: HashMap<IVec2, Tile> = HashMap::new();
+        let mut unknown: Vec<(IVec2, Color)> = Vec::new();
+        for x in 0..size.x {
+            for z in 0..size.y {
+                let p = IVec2::new(x as i32, z as i32);
+                let color = color_at(x, z);
+                let tile = palette.tile_for(&color);
+                if tile.is_none() {
+                    unknown.push((p, color));
+                }
+                tiles.insert(p, tile.unwrap_or(Tile::Outside));
+            }
+        }
+
+        Self {
+            size: size.as_ivec2(),
+            tiles,
+            unknown,
+        }
+    }
+
+    /// Whether the tile is in the outermost ring of the image.
+    pub fn is_on_border(&self, p: IVec2) -> bool {
+        p.x == 0 || p.y == 0 || p.x == self.size.x - 1 || p.y == self.size.y - 1
+    }
+}
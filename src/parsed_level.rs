@@ -0,0 +1,890 @@
+use std::ops::RangeInclusive;
+
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+
+use crate::{
+    electricity::{Channel, GridLayer},
+    palette::{Palette, Tile, TileGrid},
+};
+
+/// Everything the game needs to know about a level's map, without spawning anything.
+pub struct ParsedLevel {
+    pub grid: TileGrid,
+    pub hallways: Vec<HallwayJunction>,
+    /// Ziplines, each in order from one end to the other.
+    pub ziplines: Vec<Vec<IVec2>>,
+    /// Zipline regions without an end to start from, which can't be ridden.
+    pub closed_ziplines: Vec<Vec<IVec2>>,
+    pub chains: Vec<ChainGroup>,
+    /// For each electrical tile, the directions of its electrical neighbors.
+    pub wires: HashMap<IVec2, Vec<IVec2>>,
+    /// The power channel of each electrical tile. Tiles that are missing are [`Channel::Plain`].
+    pub channels: HashMap<IVec2, Channel>,
+    /// Wires that run between two cross floor wires, over the tiles between them.
+    pub wire_crossings: Vec<WireCrossing>,
+    /// The direction each mainframe and switch faces.
+    pub facing: HashMap<IVec2, IVec2>,
+    pub gates: HashMap<IVec2, GateSides>,
+}
+
+/// The shape and colors of a hallway region, which identifies the levels it connects.
+///
+/// Each tile is stored as its offset from the corner of the region's bounding box, along with its
+/// hallway color, sorted so that equal regions produce equal patterns.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct HallwayPattern(Vec<(IVec2, i32)>);
+
+impl HallwayPattern {
+    fn from_region(region: &HashSet<IVec2>, hallway_index: impl Fn(IVec2) -> i32) -> Self {
+        let corner = region
+            .iter()
+            .copied()
+            .reduce(IVec2::min)
+            .expect("hallway region must not be empty");
+        let mut tiles: Vec<(IVec2, i32)> = region
+            .iter()
+            .map(|&p| (p - corner, hallway_index(p)))
+            .collect();
+        tiles.sort_by_key(|(p, _)| (p.x, p.y));
+        Self(tiles)
+    }
+}
+
+#[derive(Debug)]
+pub struct HallwayJunction {
+    pub pattern: HallwayPattern,
+    pub center: Vec2,
+    pub grids: Vec<IVec2>,
+}
+
+/// A contiguous group of chain tiles.
+#[derive(Clone, Debug)]
+pub struct ChainGroup {
+    pub links: Vec<IVec2>,
+    /// The links with exactly one neighbor, which become plugs.
+    pub ends: Vec<IVec2>,
+}
+
+/// The directions from a logic gate to the wires it reads from and drives.
+#[derive(Clone, Debug)]
+pub struct GateSides {
+    pub inputs: Vec<IVec2>,
+    /// Every neighboring [`Tile::GateOutput`]. A well-formed gate has exactly one.
+    pub outputs: Vec<IVec2>,
+}
+
+#[derive(Debug)]
+pub struct WireCrossing {
+    pub from: IVec2,
+    pub direction: IVec2,
+    /// The number of tiles from `from` to the other cross floor wire.
+    pub length: i32,
+}
+
+const NEIGHBORS4: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+pub fn is_electrical(tile: &Tile) -> bool {
+    matches!(
+        tile,
+        Tile::FloorWire
+            | Tile::CrossFloorWire
+            | Tile::WallWire
+            | Tile::PowerSource
+            | Tile::Outlet
+            | Tile::ComputerMainframe
+            | Tile::ComputerMainframeBig
+            | Tile::Door
+            | Tile::Zappy
+            | Tile::Gate(_)
+            | Tile::GateOutput
+            | Tile::PressurePlate(_)
+            | Tile::Switch(_)
+            | Tile::Diode(_)
+            | Tile::ChannelWire(_)
+            | Tile::ChannelCrossing
+            | Tile::Capacitor
+            | Tile::VerticalWire
+            | Tile::Transmitter
+            | Tile::Receiver
+            | Tile::PoweredLaserSource
+            | Tile::LaserReceptor
+    )
+}
+
+impl ParsedLevel {
+    pub fn from_image(image: &Image, palette: &Palette) -> Self {
+        Self::from_grid(TileGrid::decode(image, palette))
+    }
+
+    /// Parses a level from a tightly-packed, linear RGBA8 buffer.
+    pub fn from_rgba(size: UVec2, rgba: &[u8], palette: &Palette) -> Self {
+        Self::from_grid(TileGrid::decode_rgba(size, rgba, palette))
+    }
+
+    pub fn from_grid(grid: TileGrid) -> Self {
+        let mut level = Self {
+            hallways: get_hallway_junctions(&grid),
+            ziplines: Vec::new(),
+            closed_ziplines: Vec::new(),
+            chains: get_chain_groups(&grid),
+            wires: HashMap::new(),
+            channels: HashMap::new(),
+            wire_crossings: Vec::new(),
+            facing: HashMap::new(),
+            gates: HashMap::new(),
+            grid,
+        };
+
+        (level.ziplines, level.closed_ziplines) = get_ziplines(&level.grid);
+
+        for (&p, tile) in level.grid.tiles.iter() {
+            if matches!(
+                tile,
+                Tile::ComputerMainframe | Tile::ComputerMainframeBig | Tile::Switch(_)
+            ) {
+                level.facing.insert(p, level.facing_direction(p));
+            }
+        }
+
+        for (&p, tile) in level.grid.tiles.iter() {
+            if let Some(channel) = level.infer_channel(p, *tile) {
+                level.channels.insert(p, channel);
+            }
+        }
+
+        for (&p, tile) in level.grid.tiles.iter() {
+            if matches!(tile, Tile::Gate(_)) {
+                let (outputs, inputs) = level
+                    .wire_connections(p)
+                    .into_iter()
+                    .partition(|&d| level.tile(p + d) == Tile::GateOutput);
+                level.gates.insert(p, GateSides { inputs, outputs });
+            }
+        }
+
+        for (&p, tile) in level.grid.tiles.iter() {
+            if *tile == Tile::CrossFloorWire {
+                level.wire_crossings.extend(level.crossing_from(p));
+            }
+        }
+
+        let mut wired: Vec<IVec2> = level
+            .grid
+            .tiles
+            .iter()
+            .filter(|(_, tile)| is_electrical(tile))
+            .map(|(&p, _)| p)
+            .collect();
+        for crossing in level.wire_crossings.iter() {
+            wired.extend((1..crossing.length).map(|i| crossing.from + crossing.direction * i));
+        }
+        for p in wired {
+            let connections = level.wire_connections(p);
+            level.wires.insert(p, connections);
+        }
+
+        level
+    }
+
+    /// The tile at the position, treating everything past the edge of the map as outside.
+    pub fn tile(&self, p: IVec2) -> Tile {
+        self.grid.tiles.get(&p).copied().unwrap_or(Tile::Outside)
+    }
+
+    /// Whether any solid tiles of the two levels would be in the same place, if this level's origin
+    /// were at `shift` and the other's at `other_shift`. Hallways may overlap, since they are shared.
+    pub fn overlaps(&self, shift: Vec3, other: &ParsedLevel, other_shift: Vec3) -> bool {
+        fn is_solid(tile: Tile) -> bool {
+            !matches!(tile, Tile::Outside | Tile::Hallway(_))
+        }
+
+        let offset = (shift - other_shift).round().as_ivec3().xz();
+        self.grid
+            .tiles
+            .iter()
+            .any(|(&p, &tile)| is_solid(tile) && is_solid(other.tile(p + offset)))
+    }
+
+    /// The directions from `p` towards neighboring electrical tiles. Diodes only connect to the
+    /// tiles in front of and behind them, and wires only connect to their own channel.
+    pub fn wire_connections(&self, p: IVec2) -> Vec<IVec2> {
+        let along_diode = |q: IVec2, d: IVec2| match self.tile(q) {
+            Tile::Diode(direction) => direction.offset().abs() == d.abs(),
+            _ => true,
+        };
+        // Gates, diodes and crossings connect to every channel.
+        let any_channel = |q: IVec2| {
+            matches!(
+                self.tile(q),
+                Tile::Gate(_) | Tile::Diode(_) | Tile::ChannelCrossing
+            )
+        };
+        let same_height = |q: IVec2| {
+            let (a, b) = (self.layers(p), self.layers(q));
+            a.start() <= b.end() && b.start() <= a.end()
+        };
+        NEIGHBORS4
+            .into_iter()
+            .filter(|&d| is_electrical(&self.tile(p + d)))
+            .filter(|&d| along_diode(p, d) && along_diode(p + d, d))
+            .filter(|&d| same_height(p + d))
+            .filter(|&d| {
+                any_channel(p) || any_channel(p + d) || self.channel(p) == self.channel(p + d)
+            })
+            .collect()
+    }
+
+    pub fn channel(&self, p: IVec2) -> Channel {
+        self.channels.get(&p).copied().unwrap_or_default()
+    }
+
+    /// The channel of the wire that runs from `p` into its neighbor in direction `d`. Crossings
+    /// carry each channel straight through, so they take the channel of the side it comes from.
+    pub fn channel_towards(&self, p: IVec2, d: IVec2) -> Channel {
+        if self.tile(p) == Tile::ChannelCrossing {
+            self.channel(p + d)
+        } else if self.tile(p + d) == Tile::ChannelCrossing {
+            self.channel(p)
+        } else {
+            self.channel(p + d)
+        }
+    }
+
+    /// Channel wires have their own channel, and other wires are plain. Everything else takes the
+    /// channel of the channel wires next to it, as long as they agree.
+    fn infer_channel(&self, p: IVec2, tile: Tile) -> Option<Channel> {
+        match tile {
+            Tile::ChannelWire(channel) => Some(channel),
+            Tile::FloorWire
+            | Tile::CrossFloorWire
+            | Tile::WallWire
+            | Tile::VerticalWire
+            | Tile::ChannelCrossing
+            | Tile::Gate(_)
+            | Tile::Diode(_) => None,
+            tile if is_electrical(&tile) => {
+                let neighbors: HashSet<Channel> = NEIGHBORS4
+                    .iter()
+                    .filter_map(|&d| match self.tile(p + d) {
+                        Tile::ChannelWire(channel) => Some(channel),
+                        _ => None,
+                    })
+                    .collect();
+                match neighbors.len() {
+                    1 => neighbors.into_iter().next(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_raised(&self, p: IVec2) -> bool {
+        matches!(self.tile(p), Tile::Ramp | Tile::ElevatedFloor)
+    }
+
+    /// How many blocks up the things on the tile sit. Anything beside an elevated floor or ramp,
+    /// even diagonally, is lifted onto it, except for walls.
+    pub fn height(&self, p: IVec2) -> i32 {
+        if self.is_wall(p) {
+            return 0;
+        }
+        let lifted = (-1..=1).any(|dx| (-1..=1).any(|dz| self.is_raised(p + IVec2::new(dx, dz))));
+        i32::from(lifted)
+    }
+
+    pub fn layer(&self, p: IVec2) -> GridLayer {
+        GridLayer(self.height(p))
+    }
+
+    /// The heights the tile's wires connect at. Vertical wires run from the ground up to the
+    /// elevated floors.
+    pub fn layers(&self, p: IVec2) -> RangeInclusive<i32> {
+        if self.tile(p) == Tile::VerticalWire {
+            0..=1
+        } else {
+            self.height(p)..=self.height(p)
+        }
+    }
+
+    /// Whether the tile is a wall, which switches can be mounted on.
+    pub fn is_wall(&self, p: IVec2) -> bool {
+        matches!(self.tile(p), Tile::Wall | Tile::WallWire)
+    }
+
+    fn facing_direction(&self, p: IVec2) -> IVec2 {
+        fn is_floor(t: Tile) -> bool {
+            matches!(
+                t,
+                Tile::Floor | Tile::FloorWire | Tile::CrossFloorWire | Tile::Outlet | Tile::Zipline
+            )
+        }
+
+        fn is_wall(t: Tile) -> bool {
+            matches!(t, Tile::Wall | Tile::WallWire)
+        }
+
+        let candidates = [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y];
+
+        *candidates
+            .iter()
+            .max_by_key(|&direction| {
+                let mut score = 0;
+                let forward = self.tile(p + direction);
+                if is_floor(forward) {
+                    score += 999;
+                }
+                if forward == Tile::Crate {
+                    score += 500;
+                }
+                if is_wall(self.tile(p - direction)) {
+                    score += 100;
+                }
+                score
+            })
+            .unwrap()
+    }
+
+    /// Finds the wires that run from this cross floor wire to another one.
+    fn crossing_from(&self, p: IVec2) -> Vec<WireCrossing> {
+        let mut crossings = Vec::new();
+        for direction in [IVec2::X, IVec2::Y] {
+            let mut length = 1;
+            loop {
+                if length > 6 {
+                    break;
+                }
+
+                match self.tile(p + direction * length) {
+                    Tile::Wall | Tile::Outside => {
+                        break;
+                    }
+                    Tile::CrossFloorWire => {
+                        crossings.push(WireCrossing {
+                            from: p,
+                            direction,
+                            length,
+                        });
+                        break;
+                    }
+                    t if is_electrical(&t) => {
+                        // A non-crossing thing.
+                        break;
+                    }
+                    _ => {
+                        // Do nothing
+                    }
+                }
+                length += 1;
+            }
+        }
+        crossings
+    }
+}
+
+pub fn get_hallway_junctions(grid: &TileGrid) -> Vec<HallwayJunction> {
+    let hallway_index = |p: IVec2| -> Option<i32> {
+        match grid.tiles.get(&p) {
+            Some(Tile::Hallway(index)) => Some(*index),
+            _ => None,
+        }
+    };
+    let mut visited: HashSet<IVec2> = HashSet::new();
+    let mut patterns: Vec<HallwayJunction> = Vec::new();
+
+    for x in 0..grid.size.x {
+        for y in 0..grid.size.y {
+            let p = IVec2::new(x, y);
+
+            if visited.contains(&p) || hallway_index(p).is_none() {
+                continue;
+            }
+
+            visited.insert(p);
+            let mut region: HashSet<IVec2> = HashSet::new();
+            region.insert(p);
+            let mut stack = vec![p];
+            while let Some(curr) = stack.pop() {
+                for d in NEIGHBORS4 {
+                    let neighbor = curr + d;
+                    if visited.contains(&neighbor) || hallway_index(neighbor).is_none() {
+                        continue;
+                    }
+
+                    visited.insert(neighbor);
+                    region.insert(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+
+            patterns.push(HallwayJunction {
+                pattern: HallwayPattern::from_region(&region, |p| hallway_index(p).unwrap()),
+                center: region.iter().map(|p| p.as_vec2()).sum::<Vec2>() / region.len() as f32,
+                grids: region.iter().copied().collect(),
+            })
+        }
+    }
+
+    patterns
+}
+
+/// Groups the zipline tiles into lines, ordered from one end to the other.
+fn get_ziplines(grid: &TileGrid) -> (Vec<Vec<IVec2>>, Vec<Vec<IVec2>>) {
+    fn neighbors8(p: IVec2) -> Vec<IVec2> {
+        let mut out: Vec<IVec2> = Vec::with_capacity(8);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if (dx, dy) != (0, 0) {
+                    out.push(p + IVec2::new(dx, dy));
+                }
+            }
+        }
+        out
+    }
+
+    let is_zipline = |p: IVec2| {
+        matches!(
+            grid.tiles.get(&p),
+            Some(Tile::Zipline | Tile::ZiplineOverWell)
+        )
+    };
+
+    let mut zipline_positions: Vec<IVec2> = grid
+        .tiles
+        .keys()
+        .copied()
+        .filter(|&p| is_zipline(p))
+        .collect();
+    zipline_positions.sort_by_key(|p| (p.x, p.y));
+
+    let mut ziplines: Vec<Vec<IVec2>> = Vec::new();
+    let mut visited: HashSet<IVec2> = HashSet::new();
+    for &p in zipline_positions.iter() {
+        if visited.contains(&p) {
+            continue;
+        }
+
+        if neighbors8(p)
+            .into_iter()
+            .filter(|&neighbor| is_zipline(neighbor))
+            .count()
+            != 1
+        {
+            // Only visit from the ends of a line, so that the region is built in-order.
+            continue;
+        }
+
+        visited.insert(p);
+        let mut region = vec![p];
+        let mut i = 0;
+        while i < region.len() {
+            let q = region[i];
+            for neighbor in neighbors8(q) {
+                if !visited.contains(&neighbor) && is_zipline(neighbor) {
+                    visited.insert(neighbor);
+                    region.push(neighbor);
+                }
+            }
+            i += 1;
+        }
+
+        ziplines.push(region);
+    }
+
+    // Anything left over is a region without ends.
+    let mut closed_ziplines: Vec<Vec<IVec2>> = Vec::new();
+    for &p in zipline_positions.iter() {
+        if visited.contains(&p) {
+            continue;
+        }
+        visited.insert(p);
+        let mut region = vec![p];
+        let mut i = 0;
+        while i < region.len() {
+            for neighbor in neighbors8(region[i]) {
+                if !visited.contains(&neighbor) && is_zipline(neighbor) {
+                    visited.insert(neighbor);
+                    region.push(neighbor);
+                }
+            }
+            i += 1;
+        }
+        closed_ziplines.push(region);
+    }
+
+    (ziplines, closed_ziplines)
+}
+
+/// Groups the chain tiles into separate chains.
+fn get_chain_groups(grid: &TileGrid) -> Vec<ChainGroup> {
+    let mut chain_positions: Vec<IVec2> = grid
+        .tiles
+        .iter()
+        .filter(|(_, tile)| **tile == Tile::Chain)
+        .map(|(&p, _)| p)
+        .collect();
+    chain_positions.sort_by_key(|p| (p.x, p.y));
+    let is_chain = |p: IVec2| grid.tiles.get(&p) == Some(&Tile::Chain);
+
+    let mut visited: HashSet<IVec2> = HashSet::new();
+    let mut groups: Vec<ChainGroup> = Vec::new();
+    for &p in chain_positions.iter() {
+        if visited.contains(&p) {
+            continue;
+        }
+
+        visited.insert(p);
+        let mut links = vec![p];
+        let mut i = 0;
+        while i < links.len() {
+            for d in NEIGHBORS4 {
+                let neighbor = links[i] + d;
+                if !visited.contains(&neighbor) && is_chain(neighbor) {
+                    visited.insert(neighbor);
+                    links.push(neighbor);
+                }
+            }
+            i += 1;
+        }
+
+        let ends = links
+            .iter()
+            .copied()
+            .filter(|&link| NEIGHBORS4.iter().filter(|&&d| is_chain(link + d)).count() == 1)
+            .collect();
+        groups.push(ChainGroup { links, ends });
+    }
+    groups
+}
+
+/// A hallway that can't be matched up with exactly one hallway in another level.
+#[derive(Debug)]
+pub enum HallwayError<L> {
+    /// No other level has a hallway with this pattern.
+    Unpaired { level: L, center: Vec2 },
+    /// More than two hallways share this pattern, or both are in the same level.
+    Ambiguous { levels: Vec<L>, center: Vec2 },
+}
+
+impl<L: std::fmt::Display> std::fmt::Display for HallwayError<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unpaired { level, center } => write!(
+                f,
+                "the hallway at ({}, {}) in {level} does not match any other level",
+                center.x, center.y
+            ),
+            Self::Ambiguous { levels, center } => {
+                write!(
+                    f,
+                    "the hallway at ({}, {}) has the same pattern in more than two places:",
+                    center.x, center.y
+                )?;
+                for level in levels.iter() {
+                    write!(f, " {level}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<L: std::fmt::Debug + std::fmt::Display> std::error::Error for HallwayError<L> {}
+
+/// Finds the two levels on either side of each hallway pattern.
+pub fn pair_hallways<'a, L: Clone + Eq + std::hash::Hash + 'a>(
+    levels: impl IntoIterator<Item = (&'a L, &'a ParsedLevel)>,
+) -> Result<HashMap<HallwayPattern, Vec<L>>, Vec<HallwayError<L>>> {
+    let mut junctions: HashMap<&HallwayPattern, Vec<(&L, Vec2)>> = HashMap::new();
+    for (level, parsed) in levels {
+        for junction in parsed.hallways.iter() {
+            junctions
+                .entry(&junction.pattern)
+                .or_default()
+                .push((level, junction.center));
+        }
+    }
+
+    let mut pairs: HashMap<HallwayPattern, Vec<L>> = HashMap::new();
+    let mut errors: Vec<HallwayError<L>> = Vec::new();
+    for (pattern, sides) in junctions {
+        match sides.as_slice() {
+            [(level, center)] => errors.push(HallwayError::Unpaired {
+                level: (*level).clone(),
+                center: *center,
+            }),
+            [(a, _), (b, _)] if a != b => {
+                pairs.insert(pattern.clone(), vec![(*a).clone(), (*b).clone()]);
+            }
+            _ => errors.push(HallwayError::Ambiguous {
+                levels: sides.iter().map(|(level, _)| (*level).clone()).collect(),
+                center: sides[0].1,
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(pairs)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bevy::asset::RenderAssetUsages;
+
+    use super::*;
+    use crate::electricity::DiodeDirection;
+    use crate::level_manifest::LevelManifest;
+
+    fn assets() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"))
+    }
+
+    fn read_ron<T: serde::de::DeserializeOwned>(file: &str) -> T {
+        let text = std::fs::read_to_string(assets().join(file)).unwrap();
+        ron::from_str(&text).unwrap()
+    }
+
+    fn palette() -> Palette {
+        read_ron("tiles.palette.ron")
+    }
+
+    /// Builds a level from rows of characters, one per tile.
+    fn level(rows: &[&str]) -> ParsedLevel {
+        let palette = palette();
+        let tile = |c: char| match c {
+            '.' => Tile::Floor,
+            '#' => Tile::Wall,
+            ' ' => Tile::Outside,
+            'h' => Tile::Hallway(0),
+            'H' => Tile::Hallway(1),
+            'm' => Tile::ComputerMainframe,
+            'z' => Tile::Zipline,
+            'c' => Tile::Chain,
+            '-' => Tile::FloorWire,
+            'P' => Tile::PowerSource,
+            'o' => Tile::Outlet,
+            'r' => Tile::ChannelWire(Channel::Red),
+            '>' => Tile::Diode(DiodeDirection::Right),
+            _ => panic!("unknown tile character {c:?}"),
+        };
+        let size = UVec2::new(rows[0].len() as u32, rows.len() as u32);
+        let mut rgba = Vec::new();
+        for row in rows {
+            for c in row.chars() {
+                let tile = tile(c);
+                let entry = palette
+                    .tiles
+                    .iter()
+                    .find(|entry| entry.tile == tile)
+                    .unwrap();
+                let (r, g, b) = entry.color;
+                rgba.extend([r, g, b, 255]);
+            }
+        }
+        ParsedLevel::from_rgba(size, &rgba, &palette)
+    }
+
+    #[test]
+    fn every_level_in_the_manifest_parses() {
+        let manifest: LevelManifest = read_ron("manifest.levels.ron");
+        let palette = palette();
+        manifest.validate().unwrap();
+        palette.validate().unwrap();
+
+        let mut parsed_levels: HashMap<String, ParsedLevel> = HashMap::new();
+        for entry in manifest.levels.iter() {
+            let image = image::open(assets().join(&entry.file)).unwrap();
+            let image = Image::from_dynamic(image, false, RenderAssetUsages::default());
+            let parsed = ParsedLevel::from_image(&image, &palette);
+
+            assert!(
+                parsed.grid.unknown.is_empty(),
+                "{} has colors that are not in the palette",
+                entry.file
+            );
+            assert!(
+                parsed.closed_ziplines.is_empty(),
+                "{} has a zipline without ends",
+                entry.file
+            );
+            for chain in parsed.chains.iter() {
+                assert!(
+                    chain.ends.len() >= 2,
+                    "{} has a chain at {} with fewer than 2 ends",
+                    entry.file,
+                    chain.links[0]
+                );
+            }
+            parsed_levels.insert(entry.file.clone(), parsed);
+        }
+
+        if let Err(errors) = pair_hallways(parsed_levels.iter()) {
+            panic!("hallways could not be paired: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn mainframes_face_the_floor() {
+        let parsed = level(&[
+            "#####", //
+            "##m##", //
+            "#m..#", //
+            "##..#", //
+            "#####",
+        ]);
+        assert_eq!(parsed.facing[&IVec2::new(2, 1)], IVec2::Y);
+        assert_eq!(parsed.facing[&IVec2::new(1, 2)], IVec2::X);
+    }
+
+    #[test]
+    fn hallways_with_the_same_shape_and_colors_pair() {
+        let a = level(&[
+            "#####", //
+            "#.hH#", //
+            "#####",
+        ]);
+        let b = level(&[
+            "####", //
+            "hH.#", //
+            "####",
+        ]);
+        let c = level(&[
+            "#####", //
+            "#.Hh#", //
+            "#####",
+        ]);
+        assert_eq!(a.hallways.len(), 1);
+        assert_eq!(a.hallways[0].pattern, b.hallways[0].pattern);
+        assert_ne!(a.hallways[0].pattern, c.hallways[0].pattern);
+
+        let pairs = pair_hallways([(&"a", &a), (&"b", &b)]).unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert!(pair_hallways([(&"a", &a), (&"c", &c)]).is_err());
+    }
+
+    #[test]
+    fn hallways_without_a_partner_are_unpaired() {
+        let a = level(&[
+            "#####", //
+            "#.hH#", //
+            "#####",
+        ]);
+        let c = level(&[
+            "#####", //
+            "#.Hh#", //
+            "#####",
+        ]);
+        let mut errors = pair_hallways([(&"a", &a), (&"c", &c)]).unwrap_err();
+        errors.sort_by_key(|err| match err {
+            HallwayError::Unpaired { level, .. } => *level,
+            HallwayError::Ambiguous { .. } => "",
+        });
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                HallwayError::Unpaired { level: "a", .. },
+                HallwayError::Unpaired { level: "c", .. },
+            ]
+        ));
+    }
+
+    #[test]
+    fn hallways_shared_by_three_levels_are_ambiguous() {
+        let a = level(&[
+            "#####", //
+            "#.hH#", //
+            "#####",
+        ]);
+        let b = level(&[
+            "####", //
+            "hH.#", //
+            "####",
+        ]);
+        let errors = pair_hallways([(&"a", &a), (&"b", &b), (&"also b", &b)]).unwrap_err();
+        let [HallwayError::Ambiguous { levels, .. }] = errors.as_slice() else {
+            panic!("expected one ambiguous hallway, got {errors:?}");
+        };
+        assert_eq!(levels.len(), 3);
+    }
+
+    #[test]
+    fn ziplines_are_ordered_from_end_to_end() {
+        let parsed = level(&[
+            "......", //
+            ".zz...", //
+            "...z..", //
+            "...z..", //
+            "......",
+        ]);
+        assert_eq!(
+            parsed.ziplines,
+            vec![vec![
+                IVec2::new(1, 1),
+                IVec2::new(2, 1),
+                IVec2::new(3, 2),
+                IVec2::new(3, 3),
+            ]]
+        );
+        assert!(parsed.closed_ziplines.is_empty());
+
+        let ring = level(&[
+            ".....", //
+            ".zzz.", //
+            ".z.z.", //
+            ".zzz.", //
+            ".....",
+        ]);
+        assert!(ring.ziplines.is_empty());
+        assert_eq!(ring.closed_ziplines.len(), 1);
+    }
+
+    #[test]
+    fn chains_find_their_ends() {
+        let parsed = level(&[
+            ".....", //
+            ".ccc.", //
+            "..c..", //
+            ".....",
+        ]);
+        assert_eq!(parsed.chains.len(), 1);
+        assert_eq!(parsed.chains[0].links.len(), 4);
+        assert_eq!(parsed.chains[0].ends.len(), 3);
+    }
+
+    #[test]
+    fn wires_connect_to_their_electrical_neighbors() {
+        let parsed = level(&[
+            "......", //
+            ".P--o.", //
+            "..-...", //
+            "......",
+        ]);
+        let mut connections = parsed.wires[&IVec2::new(2, 1)].clone();
+        connections.sort_by_key(|d| (d.x, d.y));
+        assert_eq!(connections, vec![IVec2::NEG_X, IVec2::Y, IVec2::X]);
+        assert_eq!(parsed.wires[&IVec2::new(4, 1)], vec![IVec2::NEG_X]);
+        assert!(!parsed.wires.contains_key(&IVec2::new(1, 2)));
+    }
+
+    #[test]
+    fn channel_wires_only_connect_to_their_channel() {
+        let parsed = level(&[
+            "......", //
+            ".-r>-.", //
+            "......",
+        ]);
+        // Plain and red wires don't connect, but diodes connect to every channel.
+        assert!(!parsed.wires[&IVec2::new(1, 1)].contains(&IVec2::X));
+        assert!(parsed.wires[&IVec2::new(2, 1)].contains(&IVec2::X));
+        assert!(parsed.wires[&IVec2::new(3, 1)].contains(&IVec2::X));
+        assert_eq!(parsed.channel(IVec2::new(2, 1)), Channel::Red);
+    }
+}
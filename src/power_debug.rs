@@ -0,0 +1,197 @@
+//! A debug overlay for the power network, toggled with F3. Only built with the `dev` feature.
+
+use bevy::{color::palettes::css, prelude::*};
+
+use crate::{
+    active_levels::ActiveLevels,
+    door::Door,
+    electricity::Powered,
+    evil_robot::EvilRobot,
+    laser::LaserEmitter,
+    mainframe::Mainframe,
+    player::PlayerCamera,
+    power_network::{NodeId, PowerNetwork, PowerRole},
+};
+
+#[derive(Resource, Default)]
+pub struct PowerDebug {
+    pub enabled: bool,
+}
+
+/// A label showing the level grid position of something drawn by the overlay.
+#[derive(Component)]
+struct PowerDebugLabel;
+
+pub struct PowerDebugPlugin;
+
+impl Plugin for PowerDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerDebug>().add_systems(
+            Update,
+            (
+                toggle_power_debug_system,
+                (draw_power_debug_system, power_debug_label_system)
+                    .run_if(|debug: Res<PowerDebug>| debug.enabled),
+                clear_power_debug_labels_system.run_if(resource_changed::<PowerDebug>),
+            )
+                .chain(),
+        );
+    }
+}
+
+fn toggle_power_debug_system(keys: Res<ButtonInput<KeyCode>>, mut debug: ResMut<PowerDebug>) {
+    if keys.just_pressed(KeyCode::F3) {
+        debug.enabled = !debug.enabled;
+    }
+}
+
+/// The world position of the top of the floor in the node's cell, or `None` if its level isn't
+/// loaded.
+fn node_position(active_levels: &ActiveLevels, node: &NodeId) -> Option<Vec3> {
+    let shift = active_levels.shift(&node.level)?;
+    Some(Vec3::new(
+        node.cell.x as f32,
+        shift.y + node.cell.y as f32 + 0.55,
+        node.cell.z as f32,
+    ))
+}
+
+/// The node's position in its level's map image, as shown by the level lint.
+fn node_label(active_levels: &ActiveLevels, node: &NodeId) -> Option<String> {
+    let shift = active_levels.shift(&node.level)?.round().as_ivec3();
+    Some(format!(
+        "{} ({}, {}) h{}",
+        node.level,
+        node.cell.x - shift.x,
+        node.cell.z - shift.z,
+        node.cell.y
+    ))
+}
+
+fn draw_power_debug_system(
+    mut gizmos: Gizmos,
+    network: Res<PowerNetwork>,
+    active_levels: Res<ActiveLevels>,
+    consumers: Query<
+        (&GlobalTransform, Has<Powered>),
+        Or<(
+            With<Mainframe>,
+            With<Door>,
+            With<EvilRobot>,
+            With<LaserEmitter>,
+        )>,
+    >,
+) {
+    let flat = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+
+    for node in network.powered_nodes() {
+        if let Some(p) = node_position(&active_levels, node) {
+            gizmos.rect(Isometry3d::new(p, flat), Vec2::splat(0.9), css::YELLOW);
+        }
+    }
+
+    for (a, b) in network.cables() {
+        if let (Some(a), Some(b)) = (
+            node_position(&active_levels, a),
+            node_position(&active_levels, b),
+        ) {
+            gizmos.line(a, b, css::AQUA);
+        }
+    }
+
+    for entity in network.entities() {
+        if network.role(entity) != Some(PowerRole::Source) {
+            continue;
+        }
+        let Some(p) = network
+            .node_of(entity)
+            .and_then(|node| node_position(&active_levels, node))
+        else {
+            continue;
+        };
+        gizmos.sphere(Isometry3d::from_translation(p), 0.3, css::LIME);
+    }
+
+    for (transform, powered) in consumers.iter() {
+        if !powered {
+            gizmos.cuboid(
+                Transform::from_translation(transform.translation()),
+                css::RED,
+            );
+        }
+    }
+}
+
+/// Labels every source and every unpowered consumer with its grid position.
+fn power_debug_label_system(
+    mut commands: Commands,
+    network: Res<PowerNetwork>,
+    active_levels: Res<ActiveLevels>,
+    camera: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+    labels: Query<Entity, With<PowerDebugLabel>>,
+    consumers: Query<
+        Entity,
+        (
+            Or<(
+                With<Mainframe>,
+                With<Door>,
+                With<EvilRobot>,
+                With<LaserEmitter>,
+            )>,
+            Without<Powered>,
+        ),
+    >,
+) {
+    for label in labels.iter() {
+        commands.entity(label).despawn();
+    }
+    let Ok((camera, camera_transform)) = camera.single() else {
+        return;
+    };
+
+    let labeled = network
+        .entities()
+        .filter(|&entity| {
+            network.role(entity) == Some(PowerRole::Source) || consumers.contains(entity)
+        })
+        .filter_map(|entity| network.node_of(entity));
+    for node in labeled {
+        let (Some(p), Some(text)) = (
+            node_position(&active_levels, node),
+            node_label(&active_levels, node),
+        ) else {
+            continue;
+        };
+        let Ok(screen) = camera.world_to_viewport(camera_transform, p + Vec3::Y * 0.5) else {
+            continue;
+        };
+        commands.spawn((
+            PowerDebugLabel,
+            Text::new(text),
+            TextFont {
+                font_size: 12.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(screen.x),
+                top: Val::Px(screen.y),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn clear_power_debug_labels_system(
+    mut commands: Commands,
+    debug: Res<PowerDebug>,
+    labels: Query<Entity, With<PowerDebugLabel>>,
+) {
+    if debug.enabled {
+        return;
+    }
+    for label in labels.iter() {
+        commands.entity(label).despawn();
+    }
+}
@@ -0,0 +1,764 @@
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{electricity::Channel, level::LevelName};
+
+/// One channel of a grid cell in a level, which is a single node in the circuit. Neighboring
+/// nodes are only connected if they are on the same channel and at the same height, or stacked
+/// on top of each other by a vertical wire.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct NodeId {
+    pub level: LevelName,
+    /// The world grid position of the cell, with its height as `y`.
+    pub cell: IVec3,
+    pub channel: Channel,
+}
+
+/// How an entity takes part in the circuit.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PowerRole {
+    /// Wires, outlets and doors, which carry power to their neighbors.
+    Conductor,
+    /// Power sources and active mainframes, which power everything they are connected to.
+    Source,
+    /// Inactive mainframes, which draw power from their neighbors without passing it on.
+    Load,
+    /// Things which are powered when their own cell is, such as robots.
+    Reader,
+    /// Capacitors and batteries giving back their stored charge. They power their net like a
+    /// source, but don't charge other capacitors.
+    Battery,
+}
+
+#[derive(Default, Debug)]
+struct Node {
+    entities: Vec<(Entity, PowerRole)>,
+    net: Option<NetId>,
+}
+
+impl Node {
+    fn conducts(&self) -> bool {
+        self.entities.iter().any(|(_, role)| {
+            matches!(
+                role,
+                PowerRole::Conductor | PowerRole::Source | PowerRole::Battery
+            )
+        })
+    }
+}
+
+type NetId = u32;
+
+/// A connected group of conducting nodes, which are either all powered or all unpowered.
+#[derive(Debug)]
+struct Net {
+    nodes: Vec<NodeId>,
+    /// Sorted, so that the same source is always reported for the net.
+    sources: Vec<Entity>,
+    /// Discharging capacitors and batteries, sorted.
+    batteries: Vec<Entity>,
+    /// Logic gates whose output is in this net, sorted.
+    drivers: Vec<Entity>,
+    powered: bool,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum GateKind {
+    And,
+    Or,
+    /// Powered when none of its inputs are.
+    Not,
+    /// Powered when an odd number of its inputs are.
+    Xor,
+}
+
+impl GateKind {
+    pub fn evaluate(&self, inputs: impl IntoIterator<Item = bool>) -> bool {
+        let mut inputs = inputs.into_iter();
+        match self {
+            Self::And => inputs.all(|input| input),
+            Self::Or => inputs.any(|input| input),
+            Self::Not => !inputs.any(|input| input),
+            Self::Xor => inputs.filter(|&input| input).count() % 2 == 1,
+        }
+    }
+}
+
+/// A logic gate, which reads the nets next to its inputs and drives the net at its output.
+#[derive(Debug)]
+struct Gate {
+    kind: GateKind,
+    node: NodeId,
+    inputs: Vec<NodeId>,
+    output: NodeId,
+    on: bool,
+}
+
+/// The circuit formed by the electrical parts of every loaded level.
+///
+/// Nodes are added when a level loads, and nets are only recomputed around nodes that changed.
+#[derive(Resource, Default)]
+pub struct PowerNetwork {
+    nodes: HashMap<NodeId, Node>,
+    entities: HashMap<Entity, NodeId>,
+    /// Cables between outlets, keyed by one of the cable's plugs.
+    cables: HashMap<Entity, (NodeId, NodeId)>,
+    nets: HashMap<NetId, Net>,
+    next_net: NetId,
+    gates: HashMap<Entity, Gate>,
+    dirty: HashSet<NodeId>,
+    /// Whether the gates must be evaluated again, even if no nets changed.
+    gates_changed: bool,
+    /// Whether anything was recomputed since [`PowerNetwork::take_changed`] was last called.
+    changed: bool,
+}
+
+const NEIGHBORS6: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+impl PowerNetwork {
+    pub fn add(&mut self, entity: Entity, node: NodeId, role: PowerRole) {
+        self.nodes
+            .entry(node.clone())
+            .or_default()
+            .entities
+            .push((entity, role));
+        self.entities.insert(entity, node.clone());
+        self.dirty.insert(node);
+    }
+
+    /// Adds a logic gate in the cell `node`, with the cells it reads from and the cell it drives.
+    pub fn add_gate(
+        &mut self,
+        entity: Entity,
+        kind: GateKind,
+        node: NodeId,
+        inputs: Vec<NodeId>,
+        output: NodeId,
+    ) {
+        self.gates.insert(
+            entity,
+            Gate {
+                kind,
+                node,
+                inputs,
+                output,
+                on: false,
+            },
+        );
+        self.gates_changed = true;
+    }
+
+    /// Takes the entity back out of the circuit, such as when a battery is unplugged.
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(node_id) = self.entities.remove(&entity) else {
+            return;
+        };
+        self.invalidate_net_of(&node_id);
+        let node = self.nodes.get_mut(&node_id).unwrap();
+        node.entities.retain(|(e, _)| *e != entity);
+        if node.entities.is_empty() {
+            self.nodes.remove(&node_id);
+        }
+        self.dirty.insert(node_id);
+    }
+
+    /// The node the entity was added to.
+    pub fn node_of(&self, entity: Entity) -> Option<&NodeId> {
+        self.entities.get(&entity)
+    }
+
+    /// The role the entity was added with.
+    pub fn role(&self, entity: Entity) -> Option<PowerRole> {
+        let node = self.entities.get(&entity)?;
+        self.nodes[node]
+            .entities
+            .iter()
+            .find(|(e, _)| *e == entity)
+            .map(|(_, role)| *role)
+    }
+
+    pub fn set_role(&mut self, entity: Entity, role: PowerRole) {
+        let Some(node_id) = self.entities.get(&entity) else {
+            return;
+        };
+        let node = self.nodes.get_mut(node_id).unwrap();
+        for (e, r) in node.entities.iter_mut() {
+            if *e == entity && *r != role {
+                *r = role;
+                self.dirty.insert(node_id.clone());
+            }
+        }
+    }
+
+    /// Removes every node in the level, along with any cables plugged into it.
+    pub fn remove_level(&mut self, level: &LevelName) {
+        let removed: Vec<NodeId> = self
+            .nodes
+            .keys()
+            .filter(|node| node.level == *level)
+            .cloned()
+            .collect();
+        for node_id in removed.iter() {
+            self.invalidate_net_of(node_id);
+            let node = self.nodes.remove(node_id).unwrap();
+            for (entity, _) in node.entities {
+                self.entities.remove(&entity);
+            }
+        }
+        self.cables
+            .retain(|_, (a, b)| a.level != *level && b.level != *level);
+        self.gates.retain(|_, gate| gate.node.level != *level);
+        self.gates_changed = true;
+    }
+
+    /// Replaces the set of cables, given as the outlets at either end of each one.
+    pub fn set_cables(&mut self, cables: impl IntoIterator<Item = (Entity, Entity, Entity)>) {
+        let mut new_cables: HashMap<Entity, (NodeId, NodeId)> = HashMap::new();
+        for (plug, a, b) in cables {
+            if let (Some(a), Some(b)) = (self.entities.get(&a), self.entities.get(&b)) {
+                new_cables.insert(plug, (a.clone(), b.clone()));
+            }
+        }
+
+        let mut changed: Vec<NodeId> = Vec::new();
+        for (plug, ends) in self.cables.iter() {
+            if new_cables.get(plug) != Some(ends) {
+                changed.extend([ends.0.clone(), ends.1.clone()]);
+            }
+        }
+        for (plug, ends) in new_cables.iter() {
+            if self.cables.get(plug) != Some(ends) {
+                changed.extend([ends.0.clone(), ends.1.clone()]);
+            }
+        }
+
+        self.cables = new_cables;
+        self.dirty.extend(changed);
+    }
+
+    fn invalidate_net_of(&mut self, node_id: &NodeId) {
+        let Some(net_id) = self.nodes.get(node_id).and_then(|node| node.net) else {
+            return;
+        };
+        let Some(net) = self.nets.remove(&net_id) else {
+            return;
+        };
+        for member in net.nodes {
+            if let Some(node) = self.nodes.get_mut(&member) {
+                node.net = None;
+            }
+            self.dirty.insert(member);
+        }
+    }
+
+    /// The conducting nodes connected directly to this one.
+    fn neighbors(&self, node_id: &NodeId) -> Vec<NodeId> {
+        let mut neighbors: Vec<NodeId> = NEIGHBORS6
+            .iter()
+            .map(|d| NodeId {
+                level: node_id.level.clone(),
+                cell: node_id.cell + *d,
+                channel: node_id.channel,
+            })
+            .collect();
+        for (a, b) in self.cables.values() {
+            if a == node_id {
+                neighbors.push(b.clone());
+            } else if b == node_id {
+                neighbors.push(a.clone());
+            }
+        }
+        neighbors.retain(|neighbor| self.nodes.get(neighbor).is_some_and(Node::conducts));
+        neighbors
+    }
+
+    /// Rebuilds the nets around any nodes that changed and evaluates the gates again, returning
+    /// whether anything did.
+    pub fn recompute(&mut self) -> bool {
+        if self.dirty.is_empty() && !self.gates_changed {
+            return false;
+        }
+
+        // A node that changed may join or split the nets of its neighbors, so rebuild those too.
+        let dirty: Vec<NodeId> = self.dirty.iter().cloned().collect();
+        for node_id in dirty.iter() {
+            self.invalidate_net_of(node_id);
+            for neighbor in self.neighbors(node_id) {
+                self.invalidate_net_of(&neighbor);
+            }
+        }
+
+        let mut gate_outputs: HashMap<NodeId, Vec<Entity>> = HashMap::new();
+        for (&entity, gate) in self.gates.iter() {
+            gate_outputs
+                .entry(gate.output.clone())
+                .or_default()
+                .push(entity);
+        }
+
+        let mut pending: Vec<NodeId> = std::mem::take(&mut self.dirty).into_iter().collect();
+        pending.sort_by_key(|node| (node.cell.x, node.cell.z, node.cell.y));
+        for start in pending {
+            let Some(node) = self.nodes.get(&start) else {
+                continue;
+            };
+            if node.net.is_some() || !node.conducts() {
+                continue;
+            }
+
+            let net_id = self.next_net;
+            self.next_net += 1;
+
+            let mut net = Net {
+                nodes: Vec::new(),
+                sources: Vec::new(),
+                batteries: Vec::new(),
+                drivers: Vec::new(),
+                powered: false,
+            };
+            self.nodes.get_mut(&start).unwrap().net = Some(net_id);
+            let mut stack = vec![start];
+            while let Some(node_id) = stack.pop() {
+                for neighbor in self.neighbors(&node_id) {
+                    let neighbor_node = self.nodes.get_mut(&neighbor).unwrap();
+                    if neighbor_node.net.is_none() {
+                        neighbor_node.net = Some(net_id);
+                        stack.push(neighbor);
+                    }
+                }
+                for &(entity, role) in self.nodes[&node_id].entities.iter() {
+                    match role {
+                        PowerRole::Source => net.sources.push(entity),
+                        PowerRole::Battery => net.batteries.push(entity),
+                        _ => {}
+                    }
+                }
+                if let Some(drivers) = gate_outputs.get(&node_id) {
+                    net.drivers.extend(drivers);
+                }
+                net.nodes.push(node_id);
+            }
+            net.sources.sort();
+            net.batteries.sort();
+            net.drivers.sort();
+            self.nets.insert(net_id, net);
+        }
+
+        self.evaluate_gates();
+        self.gates_changed = false;
+        self.changed = true;
+
+        true
+    }
+
+    /// Whether anything was recomputed since this was last called, which may have changed which
+    /// entities are powered.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Orders the gates so that each one comes after the gates that drive its inputs. Gates in a
+    /// feedback loop are ordered by position, so the loop always settles the same way.
+    fn gate_order(&self) -> Vec<Entity> {
+        let net_of = |node: &NodeId| self.nodes.get(node).and_then(|node| node.net);
+
+        let mut waiting_on: HashMap<Entity, usize> = HashMap::new();
+        let mut feeds: HashMap<Entity, Vec<Entity>> = HashMap::new();
+        for (&entity, gate) in self.gates.iter() {
+            waiting_on.insert(entity, 0);
+            let Some(output_net) = net_of(&gate.output) else {
+                continue;
+            };
+            for (&other, other_gate) in self.gates.iter() {
+                let reads_output = other_gate
+                    .inputs
+                    .iter()
+                    .any(|input| net_of(input) == Some(output_net));
+                if reads_output && other != entity {
+                    feeds.entry(entity).or_default().push(other);
+                }
+            }
+        }
+        for fed in feeds.values().flatten() {
+            *waiting_on.get_mut(fed).unwrap() += 1;
+        }
+
+        let key = |entity: &Entity| {
+            let cell = self.gates[entity].node.cell;
+            (cell.x, cell.z, cell.y, *entity)
+        };
+
+        let mut order: Vec<Entity> = Vec::with_capacity(self.gates.len());
+        while !waiting_on.is_empty() {
+            // Take the first gate with all of its inputs ready, or the first gate in a loop.
+            let next = waiting_on
+                .iter()
+                .filter(|(_, waiting)| **waiting == 0)
+                .map(|(entity, _)| *entity)
+                .min_by_key(key)
+                .or_else(|| waiting_on.keys().copied().min_by_key(key))
+                .unwrap();
+            waiting_on.remove(&next);
+            for fed in feeds.get(&next).into_iter().flatten() {
+                if let Some(waiting) = waiting_on.get_mut(fed) {
+                    *waiting = waiting.saturating_sub(1);
+                }
+            }
+            order.push(next);
+        }
+        order
+    }
+
+    /// Evaluates every gate once, in order. Each gate only sees the outputs of the gates before it,
+    /// so the result depends only on the circuit and never flips back and forth between ticks.
+    fn evaluate_gates(&mut self) {
+        for net in self.nets.values_mut() {
+            net.powered = !net.sources.is_empty() || !net.batteries.is_empty();
+        }
+        for gate in self.gates.values_mut() {
+            gate.on = false;
+        }
+
+        for entity in self.gate_order() {
+            let gate = &self.gates[&entity];
+            let on = gate.kind.evaluate(
+                gate.inputs
+                    .iter()
+                    .map(|input| self.net_of(input).is_some_and(|net| net.powered)),
+            );
+            if on && let Some(net) = self.nodes.get(&gate.output).and_then(|node| node.net) {
+                self.nets.get_mut(&net).unwrap().powered = true;
+            }
+            self.gates.get_mut(&entity).unwrap().on = on;
+        }
+    }
+
+    fn net_of(&self, node_id: &NodeId) -> Option<&Net> {
+        let net = self.nodes.get(node_id)?.net?;
+        self.nets.get(&net)
+    }
+
+    fn net_source(&self, node_id: &NodeId) -> Option<Entity> {
+        let net = self.net_of(node_id)?;
+        if !net.powered {
+            return None;
+        }
+        net.sources
+            .first()
+            .copied()
+            .or_else(|| {
+                net.drivers
+                    .iter()
+                    .copied()
+                    .find(|driver| self.gates[driver].on)
+            })
+            .or_else(|| net.batteries.first().copied())
+    }
+
+    /// The source powering the entity, if it is powered. Nets driven by a logic gate are powered
+    /// by that gate, and a gate that is on powers itself.
+    pub fn source_of(&self, entity: Entity) -> Option<Entity> {
+        if let Some(gate) = self.gates.get(&entity) {
+            return gate.on.then_some(entity);
+        }
+        let node_id = self.entities.get(&entity)?;
+        match self.role(entity)? {
+            PowerRole::Load => self
+                .neighbors(node_id)
+                .iter()
+                .find_map(|neighbor| self.net_source(neighbor)),
+            _ => self.net_source(node_id),
+        }
+    }
+
+    pub fn is_powered(&self, entity: Entity) -> bool {
+        self.source_of(entity).is_some()
+    }
+
+    /// Whether the entity's net is powered by a source or a gate, ignoring any batteries. This is
+    /// what charges capacitors.
+    pub fn is_charging(&self, entity: Entity) -> bool {
+        let Some(net) = self
+            .entities
+            .get(&entity)
+            .and_then(|node| self.net_of(node))
+        else {
+            return false;
+        };
+        !net.sources.is_empty() || net.drivers.iter().any(|driver| self.gates[driver].on)
+    }
+
+    /// Whether the entity's net is powered by anything other than the `ignored` sources.
+    pub fn is_powered_ignoring(&self, entity: Entity, ignored: &[Entity]) -> bool {
+        let Some(net) = self
+            .entities
+            .get(&entity)
+            .and_then(|node| self.net_of(node))
+        else {
+            return false;
+        };
+        net.sources.iter().any(|source| !ignored.contains(source))
+            || !net.batteries.is_empty()
+            || net.drivers.iter().any(|driver| self.gates[driver].on)
+    }
+
+    /// Whether both entities are in the same net.
+    pub fn shares_net(&self, a: Entity, b: Entity) -> bool {
+        let net = |entity| {
+            self.entities
+                .get(&entity)
+                .and_then(|node| self.nodes.get(node)?.net)
+        };
+        net(a).is_some_and(|net_a| net(b) == Some(net_a))
+    }
+
+    /// Every entity in the circuit, including logic gates.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.keys().chain(self.gates.keys()).copied()
+    }
+
+    /// Every conducting node in a powered net.
+    pub fn powered_nodes(&self) -> impl Iterator<Item = &NodeId> {
+        self.nets
+            .values()
+            .filter(|net| net.powered)
+            .flat_map(|net| net.nodes.iter())
+    }
+
+    /// The outlets joined by each cable that is plugged in at both ends.
+    pub fn cables(&self) -> impl Iterator<Item = (&NodeId, &NodeId)> {
+        self.cables.values().map(|(a, b)| (a, b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a circuit in a single row of cells, along `x`.
+    struct Circuit {
+        network: PowerNetwork,
+        level: LevelName,
+        next_entity: u32,
+    }
+
+    impl Circuit {
+        fn new() -> Self {
+            Self {
+                network: PowerNetwork::default(),
+                level: LevelName::from_string("test.png".to_string()),
+                next_entity: 0,
+            }
+        }
+
+        fn entity(&mut self) -> Entity {
+            self.next_entity += 1;
+            Entity::from_raw(self.next_entity)
+        }
+
+        fn node(&self, x: i32) -> NodeId {
+            NodeId {
+                level: self.level.clone(),
+                cell: IVec3::new(x, 0, 0),
+                channel: Channel::Plain,
+            }
+        }
+
+        fn add(&mut self, x: i32, role: PowerRole) -> Entity {
+            let entity = self.entity();
+            let node = self.node(x);
+            self.network.add(entity, node, role);
+            entity
+        }
+
+        /// The net of the node, which only changes when the net is rebuilt.
+        fn net(&self, x: i32) -> Option<NetId> {
+            self.network.nodes[&self.node(x)].net
+        }
+    }
+
+    #[test]
+    fn only_the_changed_net_is_rebuilt() {
+        let mut circuit = Circuit::new();
+        let source = circuit.add(0, PowerRole::Source);
+        circuit.add(1, PowerRole::Conductor);
+        circuit.add(10, PowerRole::Conductor);
+        circuit.add(11, PowerRole::Conductor);
+        assert!(circuit.network.recompute());
+        let (near, far) = (circuit.net(1), circuit.net(11));
+
+        circuit.network.set_role(source, PowerRole::Conductor);
+        assert!(circuit.network.recompute());
+        assert_ne!(circuit.net(1), near);
+        assert_eq!(circuit.net(11), far);
+
+        assert!(!circuit.network.recompute());
+    }
+
+    #[test]
+    fn cables_carry_power_between_outlets() {
+        let mut circuit = Circuit::new();
+        circuit.add(0, PowerRole::Source);
+        let near_outlet = circuit.add(1, PowerRole::Conductor);
+        let far_outlet = circuit.add(10, PowerRole::Conductor);
+        let far_wire = circuit.add(11, PowerRole::Conductor);
+        let plug = circuit.entity();
+        circuit.network.recompute();
+        assert!(!circuit.network.is_powered(far_wire));
+
+        circuit
+            .network
+            .set_cables([(plug, near_outlet, far_outlet)]);
+        circuit.network.recompute();
+        assert!(circuit.network.is_powered(far_wire));
+
+        circuit.network.set_cables([]);
+        circuit.network.recompute();
+        assert!(!circuit.network.is_powered(far_wire));
+    }
+
+    #[test]
+    fn removing_a_level_drops_its_nodes() {
+        let mut circuit = Circuit::new();
+        let wire = circuit.add(0, PowerRole::Source);
+        circuit.network.recompute();
+        assert!(circuit.network.is_powered(wire));
+
+        let level = circuit.level.clone();
+        circuit.network.remove_level(&level);
+        circuit.network.recompute();
+        assert!(circuit.network.nodes.is_empty());
+        assert_eq!(circuit.network.node_of(wire), None);
+        assert!(!circuit.network.is_powered(wire));
+    }
+
+    #[test]
+    fn source_of_finds_the_first_source_in_the_net() {
+        let mut circuit = Circuit::new();
+        let first = circuit.add(0, PowerRole::Source);
+        circuit.add(1, PowerRole::Conductor);
+        let second = circuit.add(2, PowerRole::Source);
+        let load = circuit.add(3, PowerRole::Load);
+        let unpowered = circuit.add(10, PowerRole::Conductor);
+        circuit.network.recompute();
+
+        assert_eq!(circuit.network.source_of(second), Some(first));
+        // Loads draw from the net next to them, without being part of it.
+        assert_eq!(circuit.network.source_of(load), Some(first));
+        assert_eq!(circuit.network.source_of(unpowered), None);
+    }
+
+    impl Circuit {
+        fn gate(&mut self, kind: GateKind, x: i32, inputs: &[i32], output: i32) -> Entity {
+            let entity = self.entity();
+            let node = self.node(x);
+            let inputs = inputs.iter().map(|&input| self.node(input)).collect();
+            let output = self.node(output);
+            self.network.add_gate(entity, kind, node, inputs, output);
+            entity
+        }
+
+        /// Evaluates the gates again, as if something in the circuit had changed.
+        fn reevaluate(&mut self) {
+            self.network.gates_changed = true;
+            self.network.recompute();
+        }
+    }
+
+    #[test]
+    fn gates_evaluate_their_truth_tables() {
+        let cases = [
+            (GateKind::And, [false, false, false, true]),
+            (GateKind::Or, [false, true, true, true]),
+            (GateKind::Not, [true, false, false, false]),
+            (GateKind::Xor, [false, true, true, false]),
+        ];
+        for (kind, expected) in cases {
+            let inputs = [[false, false], [false, true], [true, false], [true, true]];
+            for (input, expected) in inputs.into_iter().zip(expected) {
+                assert_eq!(kind.evaluate(input), expected, "{kind:?} {input:?}");
+            }
+        }
+    }
+
+    /// An AND gate reading cells 0 and 2 and driving cell 4, which a NOT gate reads to drive
+    /// cell 6. Returns whether cell 6 is powered for each pair of inputs.
+    fn and_into_not(not_first: bool) -> Vec<bool> {
+        let mut results = Vec::new();
+        for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+            let mut circuit = Circuit::new();
+            let role = |on: bool| {
+                if on {
+                    PowerRole::Source
+                } else {
+                    PowerRole::Conductor
+                }
+            };
+            circuit.add(0, role(a));
+            circuit.add(2, role(b));
+            circuit.add(4, PowerRole::Conductor);
+            let output = circuit.add(6, PowerRole::Conductor);
+            if not_first {
+                circuit.gate(GateKind::Not, 5, &[4], 6);
+                circuit.gate(GateKind::And, 1, &[0, 2], 4);
+            } else {
+                circuit.gate(GateKind::And, 1, &[0, 2], 4);
+                circuit.gate(GateKind::Not, 5, &[4], 6);
+            }
+            circuit.network.recompute();
+            results.push(circuit.network.is_powered(output));
+        }
+        results
+    }
+
+    #[test]
+    fn chained_gates_do_not_depend_on_insertion_order() {
+        assert_eq!(and_into_not(false), vec![true, true, true, false]);
+        assert_eq!(and_into_not(true), and_into_not(false));
+    }
+
+    #[test]
+    fn a_not_gate_feeding_itself_settles() {
+        let mut circuit = Circuit::new();
+        let wire = circuit.add(0, PowerRole::Conductor);
+        let gate = circuit.gate(GateKind::Not, 1, &[0], 0);
+        circuit.network.recompute();
+        let powered = circuit.network.is_powered(wire);
+        let on = circuit.network.is_powered(gate);
+        for _ in 0..10 {
+            circuit.reevaluate();
+            assert_eq!(circuit.network.is_powered(wire), powered);
+            assert_eq!(circuit.network.is_powered(gate), on);
+        }
+    }
+
+    #[test]
+    fn a_loop_of_not_gates_settles() {
+        let mut circuit = Circuit::new();
+        let a = circuit.add(0, PowerRole::Conductor);
+        let b = circuit.add(2, PowerRole::Conductor);
+        circuit.gate(GateKind::Not, 1, &[0], 2);
+        circuit.gate(GateKind::Not, 3, &[2], 0);
+        circuit.network.recompute();
+        let powered = (circuit.network.is_powered(a), circuit.network.is_powered(b));
+        // Exactly one side of the loop is on.
+        assert_ne!(powered.0, powered.1);
+        for _ in 0..10 {
+            circuit.reevaluate();
+            assert_eq!(
+                (circuit.network.is_powered(a), circuit.network.is_powered(b)),
+                powered
+            );
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    common::Common,
+    electricity::{PowerPart, compute_charge_system},
+    power_network::PowerRole,
+};
+
+/// What a pressure plate does to the circuit while it is pressed.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum PlateMode {
+    /// Powers its neighbors.
+    Source,
+    /// Connects its neighbors, like a wire.
+    Bridge,
+}
+
+/// A plate on the floor that is pressed by crates, chain balls and the player standing on it.
+#[derive(Component)]
+#[require(
+    Sensor,
+    CollidingEntities,
+    PowerPart = PowerPart::new(PowerRole::Reader)
+)]
+pub struct PressurePlate {
+    pub mode: PlateMode,
+    /// The total mass that must rest on the plate to press it. Any body presses it if this is
+    /// `None`.
+    pub min_mass: Option<f32>,
+    pub pressed: bool,
+}
+
+pub struct PressurePlatePlugin;
+
+impl Plugin for PressurePlatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (press_plate_system, visible_plate_system)
+                .chain()
+                .before(compute_charge_system),
+        );
+    }
+}
+
+fn press_plate_system(
+    mut plates: Query<(&CollidingEntities, &mut PressurePlate, &mut PowerPart)>,
+    bodies: Query<(&RigidBody, &ComputedMass)>,
+) {
+    for (colliding, mut plate, mut part) in plates.iter_mut() {
+        let mut any_body = false;
+        let mut mass = 0.0;
+        for (body, body_mass) in bodies.iter_many(colliding.iter()) {
+            if body.is_dynamic() {
+                any_body = true;
+                mass += body_mass.value();
+            }
+        }
+        let pressed = match plate.min_mass {
+            Some(min_mass) => any_body && mass >= min_mass,
+            None => any_body,
+        };
+
+        let role = match (pressed, plate.mode) {
+            (true, PlateMode::Source) => PowerRole::Source,
+            (true, PlateMode::Bridge) => PowerRole::Conductor,
+            // An unpressed plate doesn't conduct at all.
+            (false, _) => PowerRole::Reader,
+        };
+        if plate.pressed != pressed {
+            plate.pressed = pressed;
+        }
+        if part.role != role {
+            part.role = role;
+        }
+    }
+}
+
+fn visible_plate_system(
+    common: Res<Common>,
+    mut plates: Query<(&PressurePlate, &mut MeshMaterial3d<StandardMaterial>)>,
+) {
+    for (plate, mut material) in plates.iter_mut() {
+        let expected_material = if plate.pressed {
+            &common.material_electricity
+        } else {
+            &common.material_dark_blue
+        };
+        if &material.0 != expected_material {
+            material.0 = expected_material.clone();
+        }
+    }
+}
@@ -1,13 +1,19 @@
 use bevy::prelude::*;
 
-use crate::{common::Common, fog::DoesNotClearFog, level::LevelTag, player::Player};
+use crate::{common::Common, player::Player};
 
 pub struct RubyPlugin;
 
 impl Plugin for RubyPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(CanZip { can_zip: false })
-            .add_systems(Update, (collect_ruby_system, make_ruby_system));
+        app.insert_resource(CanZip { can_zip: false }).add_systems(
+            Update,
+            (
+                collect_ruby_system,
+                show_when_ruby_collected_system,
+                make_ruby_system,
+            ),
+        );
     }
 }
 
@@ -21,16 +27,15 @@ pub struct CanZip {
 
 pub fn collect_ruby_system(
     mut commands: Commands,
-    ruby: Query<(Entity, &LevelTag, &GlobalTransform), With<Ruby>>,
+    ruby: Query<(Entity, &GlobalTransform), With<Ruby>>,
     mut can_zip: ResMut<CanZip>,
     player: Query<&GlobalTransform, With<Player>>,
-    common: Res<Common>,
 ) {
     let Ok(player) = player.single() else {
         return;
     };
 
-    for (ruby_entity, ruby_level, ruby_transform) in ruby.iter() {
+    for (ruby_entity, ruby_transform) in ruby.iter() {
         if ruby_transform
             .translation()
             .xz()
@@ -40,19 +45,24 @@ pub fn collect_ruby_system(
             // Collect the ruby
             commands.entity(ruby_entity).despawn();
             can_zip.can_zip = true;
+        }
+    }
+}
 
-            // Spawn a tutorial above
-            commands.spawn((
-                ruby_level.clone(),
-                Mesh3d(common.mesh_plane.clone()),
-                MeshMaterial3d(common.material_tutorial_zip.clone()),
-                Transform::from_translation(
-                    ruby_transform.translation() + Vec3::new(0.0, 3.0, 12.0),
-                )
-                .looking_to(Vec3::Y + Vec3::Z * 0.2, -Vec3::Y)
-                .with_scale(Vec3::splat(6.)),
-                DoesNotClearFog,
-            ));
+/// Hides an entity until the player has collected the ruby.
+#[derive(Component)]
+pub struct ShowWhenRubyCollected;
+
+fn show_when_ruby_collected_system(
+    mut hidden: Query<&mut Visibility, With<ShowWhenRubyCollected>>,
+    can_zip: Res<CanZip>,
+) {
+    if !can_zip.can_zip {
+        return;
+    }
+    for mut visibility in hidden.iter_mut() {
+        if *visibility == Visibility::Hidden {
+            *visibility = Visibility::Inherited;
         }
     }
 }
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    common::Common,
+    electricity::{PowerPart, compute_charge_system},
+    interactible::{Activated, Interactible},
+    power_network::PowerRole,
+};
+
+/// How long a timed button stays on, unless the level's tile settings say otherwise.
+pub const DEFAULT_BUTTON_DURATION: f32 = 5.0;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum SwitchKind {
+    /// Flips on or off each time it is used.
+    Toggle,
+    /// Turns on for a while each time it is pressed.
+    Timed,
+}
+
+/// A wall switch or floor lever, which connects its neighbors like a wire while it is on.
+#[derive(Component)]
+#[require(
+    Interactible = Interactible::radius(1.5).with_priority(3),
+    PowerPart = PowerPart::new(PowerRole::Reader)
+)]
+pub struct Switch {
+    pub kind: SwitchKind,
+    pub on: bool,
+    /// How many seconds a timed button stays on.
+    pub duration: f32,
+    pub time_left: f32,
+}
+
+impl Switch {
+    pub fn new(kind: SwitchKind, duration: f32) -> Self {
+        Self {
+            kind,
+            on: false,
+            duration,
+            time_left: 0.0,
+        }
+    }
+}
+
+/// A bar on a timed button, which shrinks as its time runs out.
+#[derive(Component)]
+pub struct SwitchTimerBar;
+
+pub struct SwitchPlugin;
+
+impl Plugin for SwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (use_switch_system, switch_power_role_system)
+                .chain()
+                .before(compute_charge_system),
+        )
+        .add_systems(Update, (visible_switch_system, switch_timer_bar_system));
+    }
+}
+
+fn use_switch_system(time: Res<Time>, mut switches: Query<(&mut Switch, &mut Activated)>) {
+    for (mut switch, mut activated) in switches.iter_mut() {
+        let pressed = activated.take_activated();
+        match switch.kind {
+            SwitchKind::Toggle => {
+                if pressed {
+                    switch.on = !switch.on;
+                }
+            }
+            SwitchKind::Timed => {
+                if pressed {
+                    switch.time_left = switch.duration;
+                } else if switch.time_left > 0.0 {
+                    switch.time_left = (switch.time_left - time.delta_secs()).max(0.0);
+                }
+                let on = switch.time_left > 0.0;
+                if switch.on != on {
+                    switch.on = on;
+                }
+            }
+        }
+    }
+}
+
+fn switch_power_role_system(mut switches: Query<(&Switch, &mut PowerPart)>) {
+    for (switch, mut part) in switches.iter_mut() {
+        let role = if switch.on {
+            PowerRole::Conductor
+        } else {
+            PowerRole::Reader
+        };
+        if part.role != role {
+            part.role = role;
+        }
+    }
+}
+
+fn visible_switch_system(
+    common: Res<Common>,
+    mut switches: Query<(&Switch, &mut MeshMaterial3d<StandardMaterial>), Changed<Switch>>,
+) {
+    for (switch, mut material) in switches.iter_mut() {
+        let expected_material = if switch.on {
+            &common.material_electricity
+        } else {
+            &common.material_dark_blue
+        };
+        if &material.0 != expected_material {
+            material.0 = expected_material.clone();
+        }
+    }
+}
+
+fn switch_timer_bar_system(
+    switches: Query<&Switch>,
+    mut bars: Query<(&ChildOf, &mut Transform), With<SwitchTimerBar>>,
+) {
+    for (child_of, mut transform) in bars.iter_mut() {
+        let Ok(switch) = switches.get(child_of.parent()) else {
+            continue;
+        };
+        transform.scale.x = switch.time_left / switch.duration;
+    }
+}
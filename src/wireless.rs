@@ -0,0 +1,138 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{
+    active_levels::{ActiveLevels, LevelState},
+    common::Common,
+    electricity::{PowerPart, compute_charge_system},
+    level::LevelTag,
+    power_network::{PowerNetwork, PowerRole},
+};
+
+/// Sends the power of its net to every [`Receiver`] with the same link.
+#[derive(Component)]
+#[require(PowerPart = PowerPart::new(PowerRole::Conductor))]
+pub struct Transmitter {
+    pub link: String,
+}
+
+/// Powers its net while the [`Transmitter`] with the same link is powered, and otherwise
+/// conducts like a wire.
+#[derive(Component)]
+#[require(PowerPart = PowerPart::new(PowerRole::Conductor))]
+pub struct Receiver {
+    pub link: String,
+}
+
+/// Whether each link's transmitter was powered, the last time its level was loaded. Receivers
+/// keep using this while their transmitter's level is unloaded.
+#[derive(Resource)]
+pub struct RememberedLinks {
+    powered: HashMap<String, bool>,
+}
+
+impl RememberedLinks {
+    pub fn is_powered(&self, link: &str) -> bool {
+        self.powered.get(link).copied().unwrap_or(false)
+    }
+}
+
+pub struct WirelessPlugin;
+
+impl Plugin for WirelessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RememberedLinks {
+            powered: HashMap::new(),
+        });
+        app.add_systems(
+            FixedUpdate,
+            (transmit_system, receive_system)
+                .chain()
+                .after(compute_charge_system),
+        )
+        .add_systems(Update, visible_wireless_system);
+    }
+}
+
+fn transmit_system(
+    network: Res<PowerNetwork>,
+    active_levels: Res<ActiveLevels>,
+    mut remember: ResMut<RememberedLinks>,
+    transmitters: Query<(Entity, &Transmitter, &LevelTag)>,
+    receivers: Query<(Entity, &Receiver)>,
+) {
+    for (entity, transmitter, level_tag) in transmitters.iter() {
+        // Half of the level's circuit may not be spawned yet, so keep the last state until then.
+        if active_levels.state(&level_tag.level) == LevelState::Loading {
+            continue;
+        }
+
+        // The receivers of this link, and of every link it powers in turn, don't count, so that
+        // links can't keep each other powered in a loop.
+        let mut downstream = vec![transmitter.link.as_str()];
+        let mut ignored = Vec::new();
+        let mut i = 0;
+        while let Some(&link) = downstream.get(i) {
+            i += 1;
+            for (receiver_entity, receiver) in receivers.iter() {
+                if receiver.link != link {
+                    continue;
+                }
+                ignored.push(receiver_entity);
+                for (other, other_transmitter, _) in transmitters.iter() {
+                    if network.shares_net(receiver_entity, other)
+                        && !downstream.contains(&other_transmitter.link.as_str())
+                    {
+                        downstream.push(&other_transmitter.link);
+                    }
+                }
+            }
+        }
+
+        let powered = network.is_powered_ignoring(entity, &ignored);
+        if remember.is_powered(&transmitter.link) != powered {
+            remember.powered.insert(transmitter.link.clone(), powered);
+        }
+    }
+}
+
+fn receive_system(
+    remember: Res<RememberedLinks>,
+    mut receivers: Query<(&Receiver, &mut PowerPart)>,
+) {
+    for (receiver, mut part) in receivers.iter_mut() {
+        let role = if remember.is_powered(&receiver.link) {
+            PowerRole::Source
+        } else {
+            PowerRole::Conductor
+        };
+        if part.role != role {
+            part.role = role;
+        }
+    }
+}
+
+fn visible_wireless_system(
+    common: Res<Common>,
+    remember: Res<RememberedLinks>,
+    mut transmitters: Query<(&Transmitter, &mut MeshMaterial3d<StandardMaterial>)>,
+    mut receivers: Query<(&Receiver, &mut MeshMaterial3d<StandardMaterial>), Without<Transmitter>>,
+) {
+    let links = transmitters
+        .iter_mut()
+        .map(|(transmitter, material)| (&transmitter.link, material))
+        .chain(
+            receivers
+                .iter_mut()
+                .map(|(receiver, material)| (&receiver.link, material)),
+        );
+    for (link, mut material) in links {
+        let expected_material = if remember.is_powered(link) {
+            &common.material_electricity
+        } else {
+            &common.material_dark_blue
+        };
+        if &material.0 != expected_material {
+            material.0 = expected_material.clone();
+        }
+    }
+}